@@ -5,20 +5,21 @@ use ray_tracing::{ray_tracing::ray_color, scenes::*};
 
 pub fn benchmark(
     world: &ObjectList,
-    background: Vec3,
+    background: &dyn Background,
     cam: &Camera,
-    lights: &Option<ObjectList>,
+    lights: &LightList,
     rng: &mut SmallRng,
 ) {
-    let ray = cam.get_ray(rng.gen::<f64>(), rng.gen::<f64>(), rng);
-    ray_color::<ObjectList>(&ray, &world, background, &lights, 50, rng);
+    if let Some(ray) = cam.get_ray(rng.gen::<f64>(), rng.gen::<f64>(), rng) {
+        ray_color(&ray, world, background, lights, 50, rng);
+    }
 }
 
 fn criterion_benchmark(c: &mut Criterion) {
     let mut rng = SmallRng::from_entropy();
     let (world, background, cam, lights) = random_scene_light_static(1.0);
     c.bench_function("ray tracing", |b| {
-        b.iter(|| benchmark(&*world, background, &*cam, &*lights, &mut rng))
+        b.iter(|| benchmark(&world, &*background, &cam, &lights, &mut rng))
     });
 }
 
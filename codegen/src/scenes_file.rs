@@ -118,6 +118,123 @@ fn build_texture(texture: &Value) -> TokenStream {
                 }
             }
         }
+        Some("ImageTexture") => {
+            if let Some(filename) = texture["filename"].as_str() {
+                return quote! {
+                    ImageTexture::new(#filename)
+                };
+            }
+            quote! { compile_error! { "ImageTexture error" } }
+        }
+        Some("SimplexTexture") => {
+            if let Some(scale) = texture["scale"].as_f64() {
+                let seed = texture["seed"].as_u64().unwrap_or(0) as u32;
+                return quote! {
+                    SimplexTexture {
+                        scale: #scale,
+                        seed: #seed,
+                    }
+                };
+            }
+            quote! { compile_error! { "SimplexTexture error" } }
+        }
+        Some("Fbm") => {
+            if let Some(octaves) = texture["octaves"].as_u64() {
+                if let Some(lacunarity) = texture["lacunarity"].as_f64() {
+                    if let Some(gain) = texture["gain"].as_f64() {
+                        let basis = build_texture(&texture["basis"]);
+                        let octaves = octaves as usize;
+                        return quote! {
+                            Fbm {
+                                basis: #basis,
+                                octaves: #octaves,
+                                lacunarity: #lacunarity,
+                                gain: #gain,
+                            }
+                        };
+                    }
+                }
+            }
+            quote! { compile_error! { "Fbm error" } }
+        }
+        Some("Mix") => {
+            let a = build_texture(&texture["a"]);
+            let b = build_texture(&texture["b"]);
+            let factor = build_texture(&texture["factor"]);
+            quote! {
+                MixTexture {
+                    a: #a,
+                    b: #b,
+                    factor: #factor,
+                }
+            }
+        }
+        Some("Multiply") => {
+            let a = build_texture(&texture["a"]);
+            let b = build_texture(&texture["b"]);
+            quote! {
+                MultiplyTexture { a: #a, b: #b }
+            }
+        }
+        Some("Add") => {
+            let a = build_texture(&texture["a"]);
+            let b = build_texture(&texture["b"]);
+            quote! {
+                AddTexture { a: #a, b: #b }
+            }
+        }
+        Some("ColorRamp") => {
+            if let Some(stops) = texture["stops"].as_array() {
+                let mut tokens = vec![];
+                for stop in stops {
+                    if let Some(position) = stop["position"].as_f64() {
+                        let color = get_vec3(&stop["color"]);
+                        tokens.push(quote! { (#position, #color), });
+                    } else {
+                        return quote! { compile_error! { "ColorRamp stop error" } };
+                    }
+                }
+                let input = build_texture(&texture["input"]);
+                let token: TokenStream = tokens.into_iter().collect();
+                return quote! {
+                    ColorRamp {
+                        input: #input,
+                        stops: vec![#token],
+                    }
+                };
+            }
+            quote! { compile_error! { "ColorRamp error" } }
+        }
+        Some("UvTransform") => {
+            let scale_u = texture["scale_u"].as_f64().unwrap_or(1.0);
+            let scale_v = texture["scale_v"].as_f64().unwrap_or(1.0);
+            let offset_u = texture["offset_u"].as_f64().unwrap_or(0.0);
+            let offset_v = texture["offset_v"].as_f64().unwrap_or(0.0);
+            let rotation = texture["rotation"].as_f64().unwrap_or(0.0);
+            let inner = build_texture(&texture["texture"]);
+            quote! {
+                UvTransform {
+                    texture: #inner,
+                    scale: (#scale_u, #scale_v),
+                    offset: (#offset_u, #offset_v),
+                    rotation: #rotation,
+                }
+            }
+        }
+        Some("Triplanar") => {
+            if let Some(scale) = texture["scale"].as_f64() {
+                let sharpness = texture["sharpness"].as_f64().unwrap_or(4.0);
+                let inner = build_texture(&texture["texture"]);
+                return quote! {
+                    TriplanarTexture {
+                        texture: #inner,
+                        scale: #scale,
+                        sharpness: #sharpness,
+                    }
+                };
+            }
+            quote! { compile_error! { "Triplanar error" } }
+        }
         _ => {
             quote! { compile_error! { "Texture type error" } }
         }
@@ -150,11 +267,34 @@ fn get_camera(cam: &Value) -> TokenStream {
     }
     quote! { compile_error! { "Camera error" } }
 }
+fn get_projection(cam: &Value) -> TokenStream {
+    match cam["projection"].as_str() {
+        None | Some("perspective") => quote! { Projection::Perspective },
+        Some("orthographic") => quote! { Projection::Orthographic },
+        Some("panoramic") => quote! { Projection::Panoramic },
+        Some("cubemap") => quote! { Projection::CubeMap },
+        Some("fisheye") => {
+            let fov = cam["fov"].as_f64().unwrap_or(180.0);
+            let mapping = match cam["mapping"].as_str() {
+                None | Some("equidistant") => quote! { FisheyeMapping::Equidistant },
+                Some("equisolid") => quote! { FisheyeMapping::Equisolid },
+                _ => return quote! { compile_error! { "Fisheye mapping error" } },
+            };
+            quote! { Projection::Fisheye { fov: #fov, mapping: #mapping } }
+        }
+        _ => quote! { compile_error! { "Projection error" } },
+    }
+}
 fn from_file() -> (TokenStream, TokenStream) {
     let file = File::open("codegen/data/scene_500.json").unwrap();
     let reader = BufReader::new(file);
     let data: Value = serde_json::from_reader(reader).unwrap();
-    (build_object(&data["objects"]), get_camera(&data["camera"]))
+    let cam = get_camera(&data["camera"]);
+    let projection = get_projection(&data["camera"]);
+    (
+        build_object(&data["objects"]),
+        quote! { #projection.build(&#cam) },
+    )
 }
 
 pub fn scene_from_file(switch: bool) -> proc_macro::TokenStream {
@@ -165,12 +305,12 @@ pub fn scene_from_file(switch: bool) -> proc_macro::TokenStream {
     }
     let (world, cam) = from_file();
     proc_macro::TokenStream::from(quote! {
-        pub fn scene_from_file(_aspect_ratio: f64) -> (Arc<ObjectList>, Vec3, Arc<Camera>, Arc<Option<ObjectList>>) {
+        pub fn scene_from_file(_aspect_ratio: f64) -> (Arc<ObjectList>, Arc<dyn Background>, Arc<dyn CameraModel>, Arc<LightList>) {
             (
                 Arc::new(#world),
-                Vec3::zero(),
-                Arc::new(#cam),
-                Arc::new(None),
+                Arc::new(Vec3::zero()),
+                #cam,
+                Arc::new(LightList::new()),
             )
         }
     })
@@ -1,4 +1,4 @@
-use rand::Rng;
+use rand::{rngs::SmallRng, Rng};
 use std::cmp::Ordering;
 
 pub use crate::objects::*;
@@ -56,6 +56,32 @@ impl Aabb {
         }
         true
     }
+    pub fn interval(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let (mut t0, mut t1) = (t_min, t_max);
+        for &(min, max, ori, dir) in [
+            (self.min.x, self.max.x, ray.ori.x, ray.dir.x),
+            (self.min.y, self.max.y, ray.ori.y, ray.dir.y),
+            (self.min.z, self.max.z, ray.ori.z, ray.dir.z),
+        ]
+        .iter()
+        {
+            let mut ta = (min - ori) / dir;
+            let mut tb = (max - ori) / dir;
+            if ta > tb {
+                std::mem::swap(&mut ta, &mut tb);
+            }
+            t0 = t0.max(ta);
+            t1 = t1.min(tb);
+            if t1 <= t0 {
+                return None;
+            }
+        }
+        Some((t0, t1))
+    }
+    pub fn volume(&self) -> f64 {
+        let size = self.max - self.min;
+        size.x * size.y * size.z
+    }
 }
 
 pub struct Bvh {
@@ -70,11 +96,20 @@ impl Bvh {
 }
 impl Object for Bvh {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        self.root.hit(ray, t_min, t_max)
+        hit_opaque(|t_min| self.root.hit(ray, t_min, t_max), ray, t_min)
     }
     fn bounding_box(&self, t1: f64, t2: f64) -> Option<Aabb> {
         self.root.bounding_box(t1, t2)
     }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        self.root.pdf_value(origin, v)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        self.root.random(origin, rng)
+    }
+    fn hit_all(&self, ray: &Ray) -> Vec<HitRecord> {
+        self.root.hit_all(ray)
+    }
 }
 pub struct BvhNode {
     pub left: Box<dyn Object>,
@@ -130,6 +165,16 @@ impl Object for BvhNode {
     fn bounding_box(&self, _t1: f64, _t2: f64) -> Option<Aabb> {
         Some(self.boxx.clone())
     }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        0.5 * self.left.pdf_value(origin, v) + 0.5 * self.right.pdf_value(origin, v)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        if rng.gen::<f64>() < 0.5 {
+            self.left.random(origin, rng)
+        } else {
+            self.right.random(origin, rng)
+        }
+    }
 }
 
 pub struct BvhNodeStatic<T1: Object, T2: Object> {
@@ -169,6 +214,16 @@ impl<T1: Object, T2: Object> Object for BvhNodeStatic<T1, T2> {
     fn bounding_box(&self, _t1: f64, _t2: f64) -> Option<Aabb> {
         Some(self.boxx.clone())
     }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        0.5 * self.left.pdf_value(origin, v) + 0.5 * self.right.pdf_value(origin, v)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        if rng.gen::<f64>() < 0.5 {
+            self.left.random(origin, rng)
+        } else {
+            self.right.random(origin, rng)
+        }
+    }
 }
 
 fn box_x_compare(a: &dyn Object, b: &dyn Object) -> Ordering {
@@ -1,8 +1,129 @@
-use rand::{rngs::SmallRng, Rng};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use std::f64::consts::PI;
+use std::sync::Arc;
 
+pub use crate::lens::*;
+use crate::objects::Object;
 pub use crate::ray::*;
+use crate::texture::{load_image, ColorSpace};
 pub use crate::vec3::*;
 
+pub trait CameraModel: Sync + Send {
+    fn get_ray(&self, u: f64, v: f64, rng: &mut SmallRng) -> Option<Ray>;
+    fn get_ray_differential(
+        &self,
+        u: f64,
+        v: f64,
+        du: f64,
+        dv: f64,
+        rng: &mut SmallRng,
+    ) -> Option<Ray> {
+        let mut rx_rng = rng.clone();
+        let mut ry_rng = rng.clone();
+        let mut ray = self.get_ray(u, v, rng)?;
+        if let (Some(rx), Some(ry)) = (
+            self.get_ray(u + du, v, &mut rx_rng),
+            self.get_ray(u, v + dv, &mut ry_rng),
+        ) {
+            ray.differential = Some(RayDifferential {
+                rx_ori: rx.ori,
+                rx_dir: rx.dir,
+                ry_ori: ry.ori,
+                ry_dir: ry.dir,
+            });
+        }
+        Some(ray)
+    }
+    fn sample_ray(
+        &self,
+        u: f64,
+        v: f64,
+        du: f64,
+        dv: f64,
+        rng: &mut SmallRng,
+    ) -> Option<(Ray, f64)> {
+        self.get_ray_differential(u, v, du, dv, rng)
+            .map(|ray| (ray, 1.0))
+    }
+}
+
+#[derive(Clone)]
+pub struct ApertureImage {
+    pub width: usize,
+    pub height: usize,
+    pub cdf: Vec<f64>,
+}
+impl ApertureImage {
+    pub fn new(filename: &str) -> Self {
+        let (width, height, data, alpha) = load_image(filename, ColorSpace::Linear);
+        Self::from_data(
+            width,
+            height,
+            data.iter()
+                .zip(alpha.iter())
+                .map(|(c, a)| c.luminance() * a)
+                .collect(),
+        )
+    }
+    pub fn from_data(width: usize, height: usize, weight: Vec<f64>) -> Self {
+        let mut cdf = Vec::with_capacity(weight.len());
+        let mut total = 0.0;
+        for w in weight {
+            total += w.max(0.0);
+            cdf.push(total);
+        }
+        if total <= 0.0 {
+            panic!("Camera:aperture image is empty");
+        }
+        for x in cdf.iter_mut() {
+            *x /= total;
+        }
+        Self { width, height, cdf }
+    }
+    pub fn sample(&self, rng: &mut SmallRng) -> (f64, f64) {
+        let r = rng.gen::<f64>();
+        let index = self
+            .cdf
+            .partition_point(|&x| x <= r)
+            .min(self.cdf.len() - 1);
+        let (x, y) = (index % self.width, index / self.width);
+        (
+            (x as f64 + rng.gen::<f64>()) / self.width as f64 * 2.0 - 1.0,
+            1.0 - (y as f64 + rng.gen::<f64>()) / self.height as f64 * 2.0,
+        )
+    }
+}
+
+#[derive(Clone)]
+pub enum Aperture {
+    Circle,
+    Polygon { blades: usize, rotation: f64 },
+    Image(ApertureImage),
+}
+impl Aperture {
+    pub fn sample(&self, rng: &mut SmallRng) -> (f64, f64) {
+        match self {
+            Aperture::Circle => {
+                let p = random_in_unit_disk(rng);
+                (p.x, p.y)
+            }
+            Aperture::Polygon { blades, rotation } => {
+                let k = rng.gen_range(0, *blades);
+                let step = 2.0 * PI / *blades as f64;
+                let a0 = rotation.to_radians() + step * k as f64;
+                let a1 = a0 + step;
+                let (mut r1, mut r2) = (rng.gen::<f64>(), rng.gen::<f64>());
+                if r1 + r2 > 1.0 {
+                    r1 = 1.0 - r1;
+                    r2 = 1.0 - r2;
+                }
+                (a0.cos() * r1 + a1.cos() * r2, a0.sin() * r1 + a1.sin() * r2)
+            }
+            Aperture::Image(image) => image.sample(rng),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Camera {
     pub origin: Vec3,
@@ -13,10 +134,16 @@ pub struct Camera {
     pub v: Vec3,
     pub w: Vec3,
     pub lens_radius: f64,
+    pub aperture: Aperture,
+    pub cat_eye: f64,
+    pub vignetting: Vec<f64>,
     pub t1: f64,
     pub t2: f64,
 }
 impl Camera {
+    const VIGNETTING_GRID: usize = 17;
+    const LENS_TRIES: i32 = 64;
+
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         lookfrom: Vec3,
@@ -50,13 +177,153 @@ impl Camera {
             v,
             w,
             lens_radius: aperture / 2.0,
+            aperture: Aperture::Circle,
+            cat_eye: 0.0,
+            vignetting: vec![],
             t1,
             t2,
         }
     }
-    pub fn get_ray(&self, u: f64, v: f64, rng: &mut SmallRng) -> Ray {
-        let rd = random_in_unit_disk(rng) * self.lens_radius;
-        let offset = self.u * rd.x + self.v * rd.y;
+    pub fn with_aperture(self, aperture: Aperture) -> Self {
+        Self { aperture, ..self }.with_vignetting()
+    }
+    pub fn with_cat_eye(self, cat_eye: f64) -> Self {
+        Self { cat_eye, ..self }.with_vignetting()
+    }
+    fn pupil_center(&self, u: f64, v: f64) -> (f64, f64) {
+        (
+            (u - 0.5) * 2.0 * self.cat_eye,
+            (v - 0.5) * 2.0 * self.cat_eye,
+        )
+    }
+    fn circle_bounds(center: (f64, f64)) -> (f64, f64, f64, f64) {
+        (
+            f64::max(-1.0, center.0 - 1.0),
+            f64::min(1.0, center.0 + 1.0),
+            f64::max(-1.0, center.1 - 1.0),
+            f64::min(1.0, center.1 + 1.0),
+        )
+    }
+    fn circle_overlap(center: (f64, f64)) -> f64 {
+        let d = (center.0 * center.0 + center.1 * center.1).sqrt();
+        if d >= 2.0 {
+            return 0.0;
+        }
+        (2.0 * (d / 2.0).acos() - d / 2.0 * (4.0 - d * d).sqrt()) / PI
+    }
+    fn pupil_fraction(&self, center: (f64, f64), rng: &mut SmallRng) -> f64 {
+        let n = 4096;
+        let inside = (0..n)
+            .filter(|_| {
+                let (x, y) = self.aperture.sample(rng);
+                (x - center.0).powi(2) + (y - center.1).powi(2) <= 1.0
+            })
+            .count();
+        inside as f64 / n as f64
+    }
+    fn with_vignetting(self) -> Self {
+        if self.cat_eye <= 0.0 || matches!(self.aperture, Aperture::Circle) {
+            return Self {
+                vignetting: vec![],
+                ..self
+            };
+        }
+        let mut rng = SmallRng::seed_from_u64(0);
+        let n = Self::VIGNETTING_GRID;
+        let vignetting = (0..n * n)
+            .map(|i| {
+                let u = (i % n) as f64 / (n - 1) as f64;
+                let v = (i / n) as f64 / (n - 1) as f64;
+                self.pupil_fraction(self.pupil_center(u, v), &mut rng)
+            })
+            .collect();
+        Self { vignetting, ..self }
+    }
+    pub fn vignetting(&self, u: f64, v: f64) -> f64 {
+        if self.cat_eye <= 0.0 {
+            return 1.0;
+        }
+        if let Aperture::Circle = self.aperture {
+            return Self::circle_overlap(self.pupil_center(u, v));
+        }
+        let n = Self::VIGNETTING_GRID;
+        let x = num::clamp(u, 0.0, 1.0) * (n - 1) as f64;
+        let y = num::clamp(v, 0.0, 1.0) * (n - 1) as f64;
+        let (i, j) = ((x as usize).min(n - 2), (y as usize).min(n - 2));
+        let (fx, fy) = (x - i as f64, y - j as f64);
+        let at = |i: usize, j: usize| self.vignetting[j * n + i];
+        (at(i, j) * (1.0 - fx) + at(i + 1, j) * fx) * (1.0 - fy)
+            + (at(i, j + 1) * (1.0 - fx) + at(i + 1, j + 1) * fx) * fy
+    }
+    fn lens_weight(&self, u: f64, v: f64) -> f64 {
+        if self.cat_eye <= 0.0 {
+            return 1.0;
+        }
+        let fraction = self.vignetting(u, v);
+        let acceptance = match self.aperture {
+            Aperture::Circle => {
+                let (x0, x1, y0, y1) = Self::circle_bounds(self.pupil_center(u, v));
+                fraction * PI / ((x1 - x0) * (y1 - y0))
+            }
+            _ => fraction,
+        };
+        if acceptance <= 0.0 {
+            return 0.0;
+        }
+        fraction / (1.0 - (1.0 - acceptance.min(1.0)).powi(Self::LENS_TRIES))
+    }
+    pub fn focus_dist(&self) -> f64 {
+        (self.origin - self.lower_left_corner - self.horizontal / 2.0 - self.vertical / 2.0)
+            * self.w
+    }
+    pub fn with_focus_dist(self, focus_dist: f64) -> Self {
+        let ratio = focus_dist / self.focus_dist();
+        let horizontal = self.horizontal * ratio;
+        let vertical = self.vertical * ratio;
+        let lower_left_corner =
+            self.origin - horizontal / 2.0 - vertical / 2.0 - self.w * focus_dist;
+        Self {
+            horizontal,
+            vertical,
+            lower_left_corner,
+            ..self
+        }
+    }
+    pub fn focus_on(self, world: &dyn Object, u: f64, v: f64) -> Self {
+        let ray = Ray::new(
+            self.origin,
+            self.lower_left_corner + self.horizontal * u + self.vertical * v - self.origin,
+            self.t1,
+        );
+        match world.hit(&ray, 0.001, f64::MAX) {
+            Some(rec) => {
+                let focus_dist = (self.origin - rec.p) * self.w;
+                self.with_focus_dist(focus_dist)
+            }
+            None => self,
+        }
+    }
+    fn lens_sample(&self, u: f64, v: f64, rng: &mut SmallRng) -> Option<(f64, f64)> {
+        if self.cat_eye <= 0.0 {
+            return Some(self.aperture.sample(rng));
+        }
+        let center = self.pupil_center(u, v);
+        let inside = |x: f64, y: f64| (x - center.0).powi(2) + (y - center.1).powi(2) <= 1.0;
+        if let Aperture::Circle = self.aperture {
+            let (x0, x1, y0, y1) = Self::circle_bounds(center);
+            if x0 >= x1 || y0 >= y1 {
+                return None;
+            }
+            return (0..Self::LENS_TRIES)
+                .map(|_| (rng.gen_range(x0, x1), rng.gen_range(y0, y1)))
+                .find(|&(x, y)| x * x + y * y <= 1.0 && inside(x, y));
+        }
+        (0..Self::LENS_TRIES)
+            .map(|_| self.aperture.sample(rng))
+            .find(|&(x, y)| inside(x, y))
+    }
+    fn lens_ray(&self, u: f64, v: f64, lens: (f64, f64), rng: &mut SmallRng) -> Ray {
+        let offset = (self.u * lens.0 + self.v * lens.1) * self.lens_radius;
         Ray::new(
             self.origin + offset,
             self.lower_left_corner + self.horizontal * u + self.vertical * v - self.origin - offset,
@@ -64,3 +331,483 @@ impl Camera {
         )
     }
 }
+impl CameraModel for Camera {
+    fn get_ray(&self, u: f64, v: f64, rng: &mut SmallRng) -> Option<Ray> {
+        let lens = self.lens_sample(u, v, rng)?;
+        Some(self.lens_ray(u, v, lens, rng))
+    }
+    fn get_ray_differential(
+        &self,
+        u: f64,
+        v: f64,
+        du: f64,
+        dv: f64,
+        rng: &mut SmallRng,
+    ) -> Option<Ray> {
+        let ray = self.get_ray(u, v, rng)?;
+        Some(self.with_differential(ray, u, v, du, dv))
+    }
+    fn sample_ray(
+        &self,
+        u: f64,
+        v: f64,
+        du: f64,
+        dv: f64,
+        rng: &mut SmallRng,
+    ) -> Option<(Ray, f64)> {
+        let ray = self.get_ray_differential(u, v, du, dv, rng)?;
+        Some((ray, self.lens_weight(u, v)))
+    }
+}
+impl Camera {
+    fn with_differential(&self, mut ray: Ray, u: f64, v: f64, du: f64, dv: f64) -> Ray {
+        let direction = |u: f64, v: f64| {
+            self.lower_left_corner + self.horizontal * u + self.vertical * v - ray.ori
+        };
+        ray.differential = Some(RayDifferential {
+            rx_ori: ray.ori,
+            rx_dir: direction(u + du, v),
+            ry_ori: ray.ori,
+            ry_dir: direction(u, v + dv),
+        });
+        ray
+    }
+}
+
+#[derive(Clone)]
+pub struct OrthographicCamera {
+    pub lower_left_corner: Vec3,
+    pub horizontal: Vec3,
+    pub vertical: Vec3,
+    pub direction: Vec3,
+    pub t1: f64,
+    pub t2: f64,
+}
+impl OrthographicCamera {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        lookfrom: Vec3,
+        lookat: Vec3,
+        vup: Vec3,
+        height: f64,
+        aspect_ratio: f64,
+        t1: f64,
+        t2: f64,
+    ) -> Self {
+        let w = (lookfrom - lookat).unit();
+        let u = Vec3::cross(vup, w).unit();
+        let v = Vec3::cross(w, u).unit();
+        let horizontal = u * height * aspect_ratio;
+        let vertical = v * height;
+        Self {
+            lower_left_corner: lookfrom - horizontal / 2.0 - vertical / 2.0,
+            horizontal,
+            vertical,
+            direction: -w,
+            t1,
+            t2,
+        }
+    }
+    pub fn from_camera(cam: &Camera) -> Self {
+        Self {
+            lower_left_corner: cam.origin - cam.horizontal / 2.0 - cam.vertical / 2.0,
+            horizontal: cam.horizontal,
+            vertical: cam.vertical,
+            direction: -cam.w,
+            t1: cam.t1,
+            t2: cam.t2,
+        }
+    }
+}
+impl CameraModel for OrthographicCamera {
+    fn get_ray(&self, u: f64, v: f64, rng: &mut SmallRng) -> Option<Ray> {
+        Some(Ray::new(
+            self.lower_left_corner + self.horizontal * u + self.vertical * v,
+            self.direction,
+            rng.gen_range(self.t1, self.t2),
+        ))
+    }
+}
+
+#[derive(Clone)]
+pub struct PanoramicCamera {
+    pub origin: Vec3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+    pub t1: f64,
+    pub t2: f64,
+}
+impl PanoramicCamera {
+    pub fn new(lookfrom: Vec3, lookat: Vec3, vup: Vec3, t1: f64, t2: f64) -> Self {
+        let w = (lookfrom - lookat).unit();
+        let u = Vec3::cross(vup, w).unit();
+        let v = Vec3::cross(w, u).unit();
+        Self {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+            t1,
+            t2,
+        }
+    }
+    pub fn from_camera(cam: &Camera) -> Self {
+        Self {
+            origin: cam.origin,
+            u: cam.u,
+            v: cam.v,
+            w: cam.w,
+            t1: cam.t1,
+            t2: cam.t2,
+        }
+    }
+}
+impl CameraModel for PanoramicCamera {
+    fn get_ray(&self, u: f64, v: f64, rng: &mut SmallRng) -> Option<Ray> {
+        let phi = (u - 0.5) * 2.0 * PI;
+        let theta = (v - 0.5) * PI;
+        let direction =
+            (self.u * phi.sin() - self.w * phi.cos()) * theta.cos() + self.v * theta.sin();
+        Some(Ray::new(
+            self.origin,
+            direction,
+            rng.gen_range(self.t1, self.t2),
+        ))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FisheyeMapping {
+    Equidistant,
+    Equisolid,
+}
+
+#[derive(Clone)]
+pub struct FisheyeCamera {
+    pub origin: Vec3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+    pub fov: f64,
+    pub aspect_ratio: f64,
+    pub mapping: FisheyeMapping,
+    pub t1: f64,
+    pub t2: f64,
+}
+impl FisheyeCamera {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        lookfrom: Vec3,
+        lookat: Vec3,
+        vup: Vec3,
+        fov: f64,
+        aspect_ratio: f64,
+        mapping: FisheyeMapping,
+        t1: f64,
+        t2: f64,
+    ) -> Self {
+        let w = (lookfrom - lookat).unit();
+        let u = Vec3::cross(vup, w).unit();
+        let v = Vec3::cross(w, u).unit();
+        Self {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+            fov,
+            aspect_ratio,
+            mapping,
+            t1,
+            t2,
+        }
+    }
+    pub fn from_camera(cam: &Camera, fov: f64, mapping: FisheyeMapping) -> Self {
+        Self {
+            origin: cam.origin,
+            u: cam.u,
+            v: cam.v,
+            w: cam.w,
+            fov,
+            aspect_ratio: cam.horizontal.length() / cam.vertical.length(),
+            mapping,
+            t1: cam.t1,
+            t2: cam.t2,
+        }
+    }
+    fn image_point(&self, u: f64, v: f64) -> (f64, f64) {
+        ((u - 0.5) * 2.0 * self.aspect_ratio, (v - 0.5) * 2.0)
+    }
+}
+impl CameraModel for FisheyeCamera {
+    fn get_ray(&self, u: f64, v: f64, rng: &mut SmallRng) -> Option<Ray> {
+        let (x, y) = self.image_point(u, v);
+        let r = (x * x + y * y).sqrt();
+        if r > 1.0 {
+            return None;
+        }
+        let half_fov = self.fov.to_radians() / 2.0;
+        let theta = match self.mapping {
+            FisheyeMapping::Equidistant => r * half_fov,
+            FisheyeMapping::Equisolid => 2.0 * (r * (half_fov / 2.0).sin()).asin(),
+        };
+        let phi = y.atan2(x);
+        let direction =
+            (self.u * phi.cos() + self.v * phi.sin()) * theta.sin() - self.w * theta.cos();
+        Some(Ray::new(
+            self.origin,
+            direction,
+            rng.gen_range(self.t1, self.t2),
+        ))
+    }
+}
+
+#[derive(Clone)]
+pub struct CubeMapCamera {
+    pub origin: Vec3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+    pub t1: f64,
+    pub t2: f64,
+}
+impl CubeMapCamera {
+    pub fn new(lookfrom: Vec3, lookat: Vec3, vup: Vec3, t1: f64, t2: f64) -> Self {
+        let w = (lookfrom - lookat).unit();
+        let u = Vec3::cross(vup, w).unit();
+        let v = Vec3::cross(w, u).unit();
+        Self {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+            t1,
+            t2,
+        }
+    }
+    pub fn from_camera(cam: &Camera) -> Self {
+        Self {
+            origin: cam.origin,
+            u: cam.u,
+            v: cam.v,
+            w: cam.w,
+            t1: cam.t1,
+            t2: cam.t2,
+        }
+    }
+}
+impl CameraModel for CubeMapCamera {
+    fn get_ray(&self, u: f64, v: f64, rng: &mut SmallRng) -> Option<Ray> {
+        let column = num::clamp((u * 3.0).floor(), 0.0, 2.0);
+        let row = if v >= 0.5 { 0.0 } else { 1.0 };
+        let a = (u * 3.0 - column) * 2.0 - 1.0;
+        let b = (v * 2.0 - (1.0 - row)) * 2.0 - 1.0;
+        let (right, up, front) = (self.u, self.v, -self.w);
+        let direction = match (row as usize) * 3 + column as usize {
+            0 => right - front * a + up * b,
+            1 => -right + front * a + up * b,
+            2 => up + right * a - front * b,
+            3 => -up + right * a + front * b,
+            4 => front + right * a + up * b,
+            _ => -front - right * a + up * b,
+        };
+        Some(Ray::new(
+            self.origin,
+            direction,
+            rng.gen_range(self.t1, self.t2),
+        ))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective,
+    Orthographic,
+    Panoramic,
+    Fisheye { fov: f64, mapping: FisheyeMapping },
+    CubeMap,
+}
+impl Projection {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "perspective" => Ok(Projection::Perspective),
+            "orthographic" => Ok(Projection::Orthographic),
+            "panoramic" => Ok(Projection::Panoramic),
+            "fisheye" => Ok(Projection::Fisheye {
+                fov: 180.0,
+                mapping: FisheyeMapping::Equidistant,
+            }),
+            "equisolid" => Ok(Projection::Fisheye {
+                fov: 180.0,
+                mapping: FisheyeMapping::Equisolid,
+            }),
+            "cubemap" => Ok(Projection::CubeMap),
+            _ => Err(format!("Camera:unknown projection {}", name)),
+        }
+    }
+    pub fn build(self, cam: &Camera) -> Arc<dyn CameraModel> {
+        match self {
+            Projection::Perspective => Arc::new(cam.clone()),
+            Projection::Orthographic => Arc::new(OrthographicCamera::from_camera(cam)),
+            Projection::Panoramic => Arc::new(PanoramicCamera::from_camera(cam)),
+            Projection::Fisheye { fov, mapping } => {
+                Arc::new(FisheyeCamera::from_camera(cam, fov, mapping))
+            }
+            Projection::CubeMap => Arc::new(CubeMapCamera::from_camera(cam)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::*;
+
+    #[test]
+    fn test_camera_aperture() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let hexagon = Aperture::Polygon {
+            blades: 6,
+            rotation: 15.0,
+        };
+        for _ in 0..1000 {
+            let (x, y) = hexagon.sample(&mut rng);
+            for k in 0..6 {
+                let angle = (15.0 + 30.0 + 60.0 * k as f64).to_radians();
+                assert!(x * angle.cos() + y * angle.sin() <= (PI / 6.0).cos() + 1e-9);
+            }
+        }
+        let image = Aperture::Image(ApertureImage::from_data(2, 2, vec![0.0, 1.0, 0.0, 0.0]));
+        for _ in 0..100 {
+            let (x, y) = image.sample(&mut rng);
+            assert!((0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y));
+        }
+
+        let cam = Camera::new(
+            Vec3::new(0.0, 0.0, 10.0),
+            Vec3::zero(),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            2.0,
+            10.0,
+            0.0,
+            1.0,
+        )
+        .with_cat_eye(0.8);
+        assert!(cam.get_ray(0.0, 0.0, &mut rng).is_some());
+        let sphere = Sphere {
+            center: Vec3::new(0.0, 0.0, 4.0),
+            radius: 1.0,
+            material: Dielectric { ref_idx: 1.5 },
+        };
+        let cam = cam.focus_on(&sphere, 0.5, 0.5);
+        assert!((cam.focus_dist() - 5.0).abs() < 1e-9);
+        for _ in 0..100 {
+            let ray = cam.get_ray(0.5, 0.5, &mut rng).unwrap();
+            let p = ray.at((ray.ori.z - 5.0) / -ray.dir.z);
+            assert!((p - Vec3::new(0.0, 0.0, 5.0)).length() < 1e-9);
+            let ray = cam.get_ray(1.0, 0.5, &mut rng).unwrap();
+            assert!(ray.ori.x >= -0.2 - 1e-9);
+        }
+        assert!((cam.vignetting(0.5, 0.5) - 1.0).abs() < 1e-9);
+        let corner = cam.vignetting(0.0, 0.0);
+        assert!(corner > 0.0 && corner < 0.5);
+        for _ in 0..100 {
+            let (ray, weight) = cam.sample_ray(0.0, 0.0, 0.01, 0.01, &mut rng).unwrap();
+            assert!(weight >= corner && weight < corner * 1.01);
+            assert!((ray.ori.x + 0.8).powi(2) + (ray.ori.y + 0.8).powi(2) <= 1.0 + 1e-9);
+        }
+        let hexagon = cam.clone().with_aperture(hexagon);
+        let fraction = hexagon.vignetting(0.0, 0.0);
+        assert!(fraction > 0.0 && fraction < 1.0);
+        for _ in 0..100 {
+            let (ray, _) = hexagon.sample_ray(0.0, 0.0, 0.01, 0.01, &mut rng).unwrap();
+            assert!((ray.ori.x + 0.8).powi(2) + (ray.ori.y + 0.8).powi(2) <= 1.0 + 1e-9);
+        }
+        let narrow = hexagon.with_cat_eye(1.3);
+        let fraction = narrow.vignetting(0.0, 0.0);
+        let n = 20000;
+        let mean = (0..n)
+            .filter_map(|_| narrow.sample_ray(0.0, 0.0, 0.01, 0.01, &mut rng))
+            .map(|(_, weight)| weight)
+            .sum::<f64>()
+            / n as f64;
+        assert!(fraction > 0.0 && fraction < 0.1);
+        assert!((mean - fraction).abs() < 0.1 * fraction);
+        let closed = cam.with_cat_eye(1.5);
+        assert!(closed.get_ray(0.0, 0.0, &mut rng).is_none());
+        assert!(closed.sample_ray(0.0, 0.0, 0.01, 0.01, &mut rng).is_none());
+        assert_eq!(closed.vignetting(0.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_camera_projections() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let cam = Camera::new(
+            Vec3::zero(),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            90.0,
+            2.0,
+            0.0,
+            1.0,
+            0.0,
+            1.0,
+        );
+        let front = Vec3::new(0.0, 0.0, -1.0);
+        assert!(Projection::parse("cubemap") == Ok(Projection::CubeMap));
+        assert!(Projection::parse("stereographic").is_err());
+        let ortho = Projection::Orthographic.build(&cam);
+        let ray = ortho.get_ray(1.0, 0.5, &mut rng).unwrap();
+        assert!((ray.ori - Vec3::new(2.0, 0.0, 0.0)).length() < 1e-9);
+        assert!((ray.dir - front).length() < 1e-9);
+
+        let panorama = Projection::Panoramic.build(&cam);
+        for &(u, v, expected) in [
+            (0.5, 0.5, front),
+            (0.75, 0.5, Vec3::new(1.0, 0.0, 0.0)),
+            (0.0, 0.5, Vec3::new(0.0, 0.0, 1.0)),
+            (0.3, 1.0, Vec3::new(0.0, 1.0, 0.0)),
+        ]
+        .iter()
+        {
+            let ray = panorama.get_ray(u, v, &mut rng).unwrap();
+            assert!((ray.dir.unit() - expected).length() < 1e-9);
+        }
+
+        for &mapping in [FisheyeMapping::Equidistant, FisheyeMapping::Equisolid].iter() {
+            let fisheye = Projection::Fisheye {
+                fov: 180.0,
+                mapping,
+            }
+            .build(&cam);
+            let ray = fisheye.get_ray(0.5, 0.5, &mut rng).unwrap();
+            assert!((ray.dir.unit() - front).length() < 1e-9);
+            let side = fisheye.get_ray(0.75, 0.5, &mut rng).unwrap().dir.unit();
+            assert!((side - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+            assert!(fisheye.get_ray(0.5, 0.0, &mut rng).is_some());
+            assert!(fisheye.get_ray(0.0, 0.0, &mut rng).is_none());
+        }
+
+        let cube = Projection::CubeMap.build(&cam);
+        for &(u, v, expected) in [
+            (0.5 / 3.0, 0.75, Vec3::new(1.0, 0.0, 0.0)),
+            (1.5 / 3.0, 0.75, Vec3::new(-1.0, 0.0, 0.0)),
+            (2.5 / 3.0, 0.75, Vec3::new(0.0, 1.0, 0.0)),
+            (0.5 / 3.0, 0.25, Vec3::new(0.0, -1.0, 0.0)),
+            (1.5 / 3.0, 0.25, front),
+            (2.5 / 3.0, 0.25, Vec3::new(0.0, 0.0, 1.0)),
+        ]
+        .iter()
+        {
+            let ray = cube.get_ray(u, v, &mut rng).unwrap();
+            assert!((ray.dir.unit() - expected).length() < 1e-9);
+        }
+        let ray = cube
+            .get_ray_differential(1.5 / 3.0, 0.25, 0.01, 0.01, &mut rng)
+            .unwrap();
+        let differential = ray.differential.unwrap();
+        assert!(differential.rx_dir.x > ray.dir.x && differential.ry_dir.y > ray.dir.y);
+    }
+}
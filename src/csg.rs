@@ -0,0 +1,234 @@
+use rand::rngs::SmallRng;
+
+pub use crate::objects::*;
+
+fn combine<'a>(
+    left: Vec<HitRecord<'a>>,
+    right: Vec<HitRecord<'a>>,
+    op: fn(bool, bool) -> bool,
+    flip_right: bool,
+) -> Vec<HitRecord<'a>> {
+    let mut ans = vec![];
+    let (mut inside_left, mut inside_right) = (false, false);
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    loop {
+        let from_left = match (left.peek(), right.peek()) {
+            (Some(a), Some(b)) => a.t <= b.t,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+        let before = op(inside_left, inside_right);
+        let rec = if from_left {
+            inside_left = !inside_left;
+            left.next().unwrap()
+        } else {
+            inside_right = !inside_right;
+            let rec = right.next().unwrap();
+            if flip_right {
+                HitRecord {
+                    normal: -rec.normal,
+                    geometric_normal: -rec.geometric_normal,
+                    front_face: !rec.front_face,
+                    ..rec
+                }
+            } else {
+                rec
+            }
+        };
+        if op(inside_left, inside_right) != before {
+            ans.push(rec);
+        }
+    }
+    ans
+}
+
+pub struct Union<T1: Object, T2: Object> {
+    pub left: T1,
+    pub right: T2,
+}
+impl<T1: Object, T2: Object> Object for Union<T1, T2> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        if let Some(boxx) = self.bounding_box(0.0, 1.0) {
+            if !boxx.hit(ray, t_min, t_max) {
+                return None;
+            }
+        }
+        self.hit_all(ray)
+            .into_iter()
+            .find(|rec| rec.t > t_min && rec.t < t_max)
+    }
+    fn bounding_box(&self, t1: f64, t2: f64) -> Option<Aabb> {
+        Some(Aabb::surrounding_box(
+            self.left.bounding_box(t1, t2)?,
+            self.right.bounding_box(t1, t2)?,
+        ))
+    }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        volume_pdf_value(self, origin, v)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        volume_random(self, origin, rng)
+    }
+    fn hit_all(&self, ray: &Ray) -> Vec<HitRecord> {
+        combine(
+            self.left.hit_all(ray),
+            self.right.hit_all(ray),
+            |a, b| a || b,
+            false,
+        )
+    }
+}
+
+pub struct Intersection<T1: Object, T2: Object> {
+    pub left: T1,
+    pub right: T2,
+}
+impl<T1: Object, T2: Object> Object for Intersection<T1, T2> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        if let Some(boxx) = self.bounding_box(0.0, 1.0) {
+            if !boxx.hit(ray, t_min, t_max) {
+                return None;
+            }
+        }
+        self.hit_all(ray)
+            .into_iter()
+            .find(|rec| rec.t > t_min && rec.t < t_max)
+    }
+    fn bounding_box(&self, t1: f64, t2: f64) -> Option<Aabb> {
+        match (
+            self.left.bounding_box(t1, t2),
+            self.right.bounding_box(t1, t2),
+        ) {
+            (Some(a), Some(b)) => Some(Aabb {
+                min: Vec3::new(
+                    a.min.x.max(b.min.x),
+                    a.min.y.max(b.min.y),
+                    a.min.z.max(b.min.z),
+                ),
+                max: Vec3::new(
+                    a.max.x.min(b.max.x),
+                    a.max.y.min(b.max.y),
+                    a.max.z.min(b.max.z),
+                ),
+            }),
+            (Some(a), None) => Some(a),
+            (None, b) => b,
+        }
+    }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        volume_pdf_value(self, origin, v)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        volume_random(self, origin, rng)
+    }
+    fn hit_all(&self, ray: &Ray) -> Vec<HitRecord> {
+        combine(
+            self.left.hit_all(ray),
+            self.right.hit_all(ray),
+            |a, b| a && b,
+            false,
+        )
+    }
+}
+
+pub struct Difference<T1: Object, T2: Object> {
+    pub left: T1,
+    pub right: T2,
+}
+impl<T1: Object, T2: Object> Object for Difference<T1, T2> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        if let Some(boxx) = self.bounding_box(0.0, 1.0) {
+            if !boxx.hit(ray, t_min, t_max) {
+                return None;
+            }
+        }
+        self.hit_all(ray)
+            .into_iter()
+            .find(|rec| rec.t > t_min && rec.t < t_max)
+    }
+    fn bounding_box(&self, t1: f64, t2: f64) -> Option<Aabb> {
+        self.left.bounding_box(t1, t2)
+    }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        volume_pdf_value(self, origin, v)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        volume_random(self, origin, rng)
+    }
+    fn hit_all(&self, ray: &Ray) -> Vec<HitRecord> {
+        combine(
+            self.left.hit_all(ray),
+            self.right.hit_all(ray),
+            |a, b| a && !b,
+            true,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn sphere(x: f64, radius: f64) -> Sphere<Dielectric> {
+        Sphere {
+            center: Vec3::new(x, 0.0, 0.0),
+            radius,
+            material: Dielectric { ref_idx: 1.5 },
+        }
+    }
+
+    #[test]
+    fn test_csg_hit_all() {
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        let lens = Intersection {
+            left: sphere(-0.5, 1.0),
+            right: sphere(0.5, 1.0),
+        };
+        let t: Vec<f64> = lens.hit_all(&ray).iter().map(|x| x.t).collect();
+        assert_eq!(t.len(), 2);
+        assert!((t[0] - 4.5).abs() < 1e-6 && (t[1] - 5.5).abs() < 1e-6);
+
+        let shell = Difference {
+            left: sphere(0.0, 2.0),
+            right: sphere(0.0, 1.0),
+        };
+        let hits = shell.hit_all(&ray);
+        let t: Vec<f64> = hits.iter().map(|x| x.t).collect();
+        assert_eq!(t.len(), 4);
+        assert!((t[1] - 4.0).abs() < 1e-6 && (t[2] - 6.0).abs() < 1e-6);
+        assert!(hits[1].normal.x > 0.0 && hits[2].normal.x < 0.0);
+        let rec = shell.hit(&ray, 4.5, f64::MAX).unwrap();
+        assert!((rec.t - 6.0).abs() < 1e-6);
+
+        let union = Union {
+            left: sphere(-0.5, 1.0),
+            right: sphere(0.5, 1.0),
+        };
+        let t: Vec<f64> = union.hit_all(&ray).iter().map(|x| x.t).collect();
+        assert_eq!(t.len(), 2);
+        assert!((t[0] - 3.5).abs() < 1e-6 && (t[1] - 6.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_csg_pdf() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let lens = Intersection {
+            left: sphere(-0.5, 1.0),
+            right: sphere(0.5, 1.0),
+        };
+        let origin = Vec3::new(0.3, 2.0, -1.5);
+        let n = 200000;
+        let mean = (0..n)
+            .map(|_| lens.pdf_value(origin, random_unit_vector(&mut rng)))
+            .sum::<f64>()
+            / n as f64;
+        assert!((4.0 * std::f64::consts::PI * mean - 1.0).abs() < 0.05);
+        for _ in 0..100 {
+            let v = lens.random(origin, &mut rng);
+            assert!(lens.pdf_value(origin, v) > 0.0);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use rand::{rngs::SmallRng, Rng};
+use std::f64::consts::PI;
+
+pub use crate::lights::*;
+
+pub trait Background: Sync + Send {
+    fn value(&self, direction: Vec3) -> Vec3;
+}
+impl Background for Vec3 {
+    fn value(&self, _direction: Vec3) -> Vec3 {
+        *self
+    }
+}
+
+pub struct EnvironmentMap {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<Vec3>,
+    pub rotation: f64,
+    pub intensity: f64,
+    pub scene_radius: f64,
+    pub weight: Vec<f64>,
+    pub total: f64,
+    pub marginal: Vec<f64>,
+    pub conditional: Vec<Vec<f64>>,
+    pub luminance: f64,
+}
+impl EnvironmentMap {
+    pub fn new(filename: &str, rotation: f64, intensity: f64, scene_radius: f64) -> Self {
+        let (width, height, data, _) = load_image(filename, ColorSpace::Srgb);
+        Self::from_data(width, height, data, rotation, intensity, scene_radius)
+    }
+    pub fn from_data(
+        width: usize,
+        height: usize,
+        data: Vec<Vec3>,
+        rotation: f64,
+        intensity: f64,
+        scene_radius: f64,
+    ) -> Self {
+        let mut weight = vec![0.0; width * height];
+        let mut marginal = vec![0.0; height];
+        let mut conditional = vec![vec![0.0; width]; height];
+        let mut total = 0.0;
+        let mut solid_angle = 0.0;
+        for j in 0..height {
+            let sin_theta = ((j as f64 + 0.5) / height as f64 * PI).sin();
+            let mut row = 0.0;
+            for i in 0..width {
+                let w = data[j * width + i].luminance().max(0.0) * sin_theta;
+                weight[j * width + i] = w;
+                row += w;
+                conditional[j][i] = row;
+            }
+            for x in conditional[j].iter_mut() {
+                *x = if row > 0.0 { *x / row } else { 0.0 };
+            }
+            total += row;
+            marginal[j] = total;
+            solid_angle += sin_theta * width as f64;
+        }
+        for x in marginal.iter_mut() {
+            *x /= total.max(f64::MIN_POSITIVE);
+        }
+        Self {
+            width,
+            height,
+            data,
+            rotation,
+            intensity,
+            scene_radius,
+            weight,
+            total,
+            marginal,
+            conditional,
+            luminance: total / solid_angle * intensity,
+        }
+    }
+    fn uv(&self, direction: Vec3) -> (f64, f64) {
+        let d = direction.unit();
+        let theta = num::clamp(d.y, -1.0, 1.0).acos();
+        let phi = d.z.atan2(d.x) + self.rotation.to_radians();
+        ((phi / (2.0 * PI)).rem_euclid(1.0), theta / PI)
+    }
+    fn pixel(&self, u: f64, v: f64) -> usize {
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+        j * self.width + i
+    }
+    fn search(cdf: &[f64], x: f64) -> usize {
+        cdf.iter().position(|&c| c > x).unwrap_or(cdf.len() - 1)
+    }
+}
+impl Background for EnvironmentMap {
+    fn value(&self, direction: Vec3) -> Vec3 {
+        let (u, v) = self.uv(direction);
+        self.data[self.pixel(u, v)] * self.intensity
+    }
+}
+impl Light for EnvironmentMap {
+    fn power(&self) -> f64 {
+        PI * PI * self.scene_radius * self.scene_radius * self.luminance
+    }
+    fn pdf_value(&self, _origin: Vec3, v: Vec3) -> f64 {
+        if self.total <= 0.0 {
+            return 0.0;
+        }
+        let (u, v) = self.uv(v);
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        self.weight[self.pixel(u, v)] / self.total * (self.width * self.height) as f64
+            / (2.0 * PI * PI * sin_theta)
+    }
+    fn random(&self, _origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        let j = Self::search(&self.marginal, rng.gen::<f64>());
+        let i = Self::search(&self.conditional[j], rng.gen::<f64>());
+        let u = (i as f64 + rng.gen::<f64>()) / self.width as f64;
+        let v = (j as f64 + rng.gen::<f64>()) / self.height as f64;
+        let theta = v * PI;
+        let phi = u * 2.0 * PI - self.rotation.to_radians();
+        Vec3::new(
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            theta.sin() * phi.sin(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_environment_pdf() {
+        let (width, height) = (16, 8);
+        let data = (0..width * height)
+            .map(|x| Vec3::ones() * (1.0 + (x % 7) as f64))
+            .collect();
+        let env = EnvironmentMap::from_data(width, height, data, 30.0, 1.0, 1.0);
+        let mut rng = SmallRng::seed_from_u64(0);
+        let n = 100000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let d = random_unit_vector(&mut rng);
+            sum += env.pdf_value(Vec3::zero(), d) * 4.0 * PI;
+        }
+        assert!((sum / n as f64 - 1.0).abs() < 0.02);
+        for _ in 0..100 {
+            let d = env.random(Vec3::zero(), &mut rng);
+            assert!(env.pdf_value(Vec3::zero(), d) > 0.0);
+        }
+    }
+}
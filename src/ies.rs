@@ -0,0 +1,130 @@
+#[derive(Clone)]
+pub struct IesProfile {
+    pub vertical_angles: Vec<f64>,
+    pub horizontal_angles: Vec<f64>,
+    pub candela: Vec<Vec<f64>>,
+    pub max_candela: f64,
+}
+impl IesProfile {
+    pub fn new(filename: &str) -> Self {
+        Self::parse(&std::fs::read_to_string(filename).expect("IesProfile:failed to open file"))
+    }
+    pub fn parse(content: &str) -> Self {
+        let mut lines = content.lines();
+        let tilt = loop {
+            match lines.next() {
+                Some(line) if line.trim_start().starts_with("TILT=") => {
+                    break line.trim_start()["TILT=".len()..].trim().to_string();
+                }
+                Some(_) => {}
+                None => panic!("IesProfile:missing TILT line"),
+            }
+        };
+        let rest: Vec<&str> = lines.collect();
+        let mut values = rest.iter().flat_map(|x| x.split_whitespace()).map(|x| {
+            x.parse::<f64>()
+                .expect("IesProfile:invalid number in photometric data")
+        });
+        let mut next = || values.next().expect("IesProfile:unexpected end of file");
+        if tilt == "INCLUDE" {
+            next();
+            let n = next() as usize;
+            for _ in 0..2 * n {
+                next();
+            }
+        }
+        let _num_lamps = next();
+        let _lumens_per_lamp = next();
+        let multiplier = next();
+        let n_vertical = next() as usize;
+        let n_horizontal = next() as usize;
+        for _ in 0..8 {
+            next();
+        }
+        let vertical_angles: Vec<f64> = (0..n_vertical).map(|_| next()).collect();
+        let horizontal_angles: Vec<f64> = (0..n_horizontal).map(|_| next()).collect();
+        let candela: Vec<Vec<f64>> = (0..n_horizontal)
+            .map(|_| (0..n_vertical).map(|_| next() * multiplier).collect())
+            .collect();
+        let max_candela = candela.iter().flatten().cloned().fold(0.0, f64::max);
+        Self {
+            vertical_angles,
+            horizontal_angles,
+            candela,
+            max_candela,
+        }
+    }
+    fn interpolate(angles: &[f64], x: f64) -> Option<(usize, usize, f64)> {
+        if angles.len() == 1 {
+            return Some((0, 0, 0.0));
+        }
+        if x < angles[0] || x > angles[angles.len() - 1] {
+            return None;
+        }
+        let i = angles
+            .iter()
+            .position(|&a| a > x)
+            .unwrap_or(angles.len() - 1)
+            .max(1);
+        let t = (x - angles[i - 1]) / (angles[i] - angles[i - 1]);
+        Some((i - 1, i, t))
+    }
+    pub fn value(&self, vertical: f64, horizontal: f64) -> f64 {
+        let last = self.horizontal_angles[self.horizontal_angles.len() - 1];
+        let mut h = horizontal.rem_euclid(360.0);
+        if last <= 0.0 {
+            h = 0.0;
+        } else if last <= 90.0 {
+            h = if h > 180.0 { 360.0 - h } else { h };
+            h = if h > 90.0 { 180.0 - h } else { h };
+        } else if last <= 180.0 && h > 180.0 {
+            h = 360.0 - h;
+        }
+        let (v1, v2, tv) = match Self::interpolate(&self.vertical_angles, vertical) {
+            Some(x) => x,
+            None => return 0.0,
+        };
+        let (h1, h2, th) = match Self::interpolate(&self.horizontal_angles, h) {
+            Some(x) => x,
+            None => return 0.0,
+        };
+        let row = |h: usize| self.candela[h][v1] * (1.0 - tv) + self.candela[h][v2] * tv;
+        row(h1) * (1.0 - th) + row(h2) * th
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILE: &str = "IESNA:LM-63-2002
+[TEST] test
+TILT=NONE
+1 1000 2.0 3 2 1 2 0.1 0.1 0.0
+1.0 1.0 50
+0 45 90
+0 90
+100 50 0
+100 30 0
+";
+
+    #[test]
+    fn test_parse() {
+        let profile = IesProfile::parse(PROFILE);
+        assert_eq!(profile.vertical_angles, vec![0.0, 45.0, 90.0]);
+        assert_eq!(profile.horizontal_angles, vec![0.0, 90.0]);
+        assert_eq!(profile.candela[1], vec![200.0, 60.0, 0.0]);
+        assert_eq!(profile.max_candela, 200.0);
+    }
+
+    #[test]
+    fn test_value() {
+        let profile = IesProfile::parse(PROFILE);
+        assert!((profile.value(0.0, 0.0) - 200.0).abs() < 1e-9);
+        assert!((profile.value(22.5, 0.0) - 150.0).abs() < 1e-9);
+        assert!((profile.value(45.0, 45.0) - 80.0).abs() < 1e-9);
+        assert!((profile.value(45.0, 270.0) - 60.0).abs() < 1e-9);
+        assert!((profile.value(45.0, 180.0) - 100.0).abs() < 1e-9);
+        assert_eq!(profile.value(120.0, 0.0), 0.0);
+    }
+}
@@ -0,0 +1,406 @@
+use rand::{rngs::SmallRng, Rng};
+
+use crate::camera::CameraModel;
+use crate::ray::*;
+use crate::vec3::*;
+
+#[derive(Clone, Copy, Debug)]
+pub struct LensElement {
+    pub curvature_radius: f64,
+    pub thickness: f64,
+    pub eta: f64,
+    pub aperture_radius: f64,
+}
+impl LensElement {
+    pub fn is_stop(&self) -> bool {
+        self.curvature_radius == 0.0
+    }
+}
+
+pub fn load_lens(filename: &str) -> Vec<LensElement> {
+    parse_lens(&std::fs::read_to_string(filename).expect("LensElement:failed to open file"))
+}
+pub fn parse_lens(content: &str) -> Vec<LensElement> {
+    let mut elements = vec![];
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let values: Vec<f64> = line
+            .split_whitespace()
+            .map(|x| x.parse().expect("LensElement:invalid number"))
+            .collect();
+        if values.len() != 4 {
+            panic!("LensElement:expected radius, thickness, ior and aperture");
+        }
+        elements.push(LensElement {
+            curvature_radius: values[0],
+            thickness: values[1],
+            eta: if values[2] == 0.0 { 1.0 } else { values[2] },
+            aperture_radius: values[3] / 2.0,
+        });
+    }
+    if elements.is_empty() {
+        panic!("LensElement:empty prescription");
+    }
+    elements
+}
+
+fn refract(d: Vec3, n: Vec3, eta: f64) -> Option<Vec3> {
+    let cos_i = -(d * n);
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i).max(0.0);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(d * eta + n * (eta * cos_i - cos_t))
+}
+fn intersect_element(ori: Vec3, dir: Vec3, z: f64, radius: f64) -> Option<(f64, Vec3)> {
+    if radius == 0.0 {
+        if dir.z == 0.0 {
+            return None;
+        }
+        let t = (z - ori.z) / dir.z;
+        return Some((t, Vec3::new(0.0, 0.0, -dir.z.signum())));
+    }
+    let o = ori - Vec3::new(0.0, 0.0, z + radius);
+    let a = dir.squared_length();
+    let half_b = o * dir;
+    let c = o.squared_length() - radius * radius;
+    let discriminant = half_b * half_b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    let (t0, t1) = ((-half_b - root) / a, (-half_b + root) / a);
+    let t = if (dir.z > 0.0) != (radius < 0.0) {
+        t0
+    } else {
+        t1
+    };
+    if t < 0.0 {
+        return None;
+    }
+    let mut n = (o + dir * t).unit();
+    if n * dir > 0.0 {
+        n = -n;
+    }
+    Some((t, n))
+}
+
+#[derive(Clone)]
+pub struct RealisticCamera {
+    pub origin: Vec3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+    pub elements: Vec<LensElement>,
+    pub scale: f64,
+    pub film_width: f64,
+    pub film_height: f64,
+    pub exit_pupils: Vec<(f64, f64, f64, f64)>,
+    pub reference_area: f64,
+    pub t1: f64,
+    pub t2: f64,
+}
+impl RealisticCamera {
+    const PUPIL_BINS: usize = 32;
+    const PUPIL_SAMPLES: usize = 48;
+    const CLOSEST_FOCUS: f64 = 1.1;
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        lookfrom: Vec3,
+        lookat: Vec3,
+        vup: Vec3,
+        mut elements: Vec<LensElement>,
+        aperture_diameter: f64,
+        focus_dist: f64,
+        film_diagonal: f64,
+        aspect_ratio: f64,
+        scale: f64,
+        t1: f64,
+        t2: f64,
+    ) -> Self {
+        for element in elements.iter_mut().filter(|e| e.is_stop()) {
+            element.aperture_radius = element.aperture_radius.min(aperture_diameter / 2.0);
+        }
+        let w = (lookfrom - lookat).unit();
+        let u = Vec3::cross(vup, w).unit();
+        let v = Vec3::cross(w, u).unit();
+        let film_height = film_diagonal / (1.0 + aspect_ratio * aspect_ratio).sqrt();
+        let mut cam = Self {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+            elements,
+            scale,
+            film_width: film_height * aspect_ratio,
+            film_height,
+            exit_pupils: vec![],
+            reference_area: 1.0,
+            t1,
+            t2,
+        };
+        cam.focus(focus_dist);
+        cam.compute_exit_pupils();
+        cam
+    }
+    pub fn rear_z(&self) -> f64 {
+        -self.elements.last().unwrap().thickness
+    }
+    pub fn front_z(&self) -> f64 {
+        -self.elements.iter().map(|e| e.thickness).sum::<f64>()
+    }
+    pub fn trace_from_film(&self, mut ori: Vec3, mut dir: Vec3) -> Option<(Vec3, Vec3)> {
+        let mut z = 0.0;
+        for (i, element) in self.elements.iter().enumerate().rev() {
+            z -= element.thickness;
+            let (t, n) = intersect_element(ori, dir, z, element.curvature_radius)?;
+            ori += dir * t;
+            if ori.x * ori.x + ori.y * ori.y > element.aperture_radius * element.aperture_radius {
+                return None;
+            }
+            if !element.is_stop() {
+                let eta_t = if i > 0 { self.elements[i - 1].eta } else { 1.0 };
+                dir = refract(dir.unit(), n, element.eta / eta_t)?;
+            }
+        }
+        Some((ori, dir))
+    }
+    pub fn trace_from_scene(&self, mut ori: Vec3, mut dir: Vec3) -> Option<(Vec3, Vec3)> {
+        let mut z = self.front_z();
+        for (i, element) in self.elements.iter().enumerate() {
+            let (t, n) = intersect_element(ori, dir, z, element.curvature_radius)?;
+            ori += dir * t;
+            if ori.x * ori.x + ori.y * ori.y > element.aperture_radius * element.aperture_radius {
+                return None;
+            }
+            if !element.is_stop() {
+                let eta_i = if i > 0 { self.elements[i - 1].eta } else { 1.0 };
+                dir = refract(dir.unit(), n, eta_i / element.eta)?;
+            }
+            z += element.thickness;
+        }
+        Some((ori, dir))
+    }
+    fn cardinal_points(height: f64, ori: Vec3, dir: Vec3) -> (f64, f64) {
+        let principal = ori.z + (height - ori.x) / dir.x * dir.z;
+        let focal = ori.z - ori.x / dir.x * dir.z;
+        (principal, focal)
+    }
+    pub fn thick_lens(&self) -> ((f64, f64), (f64, f64)) {
+        let height = 0.001
+            * (self.film_width * self.film_width + self.film_height * self.film_height).sqrt();
+        let (ori, dir) = self
+            .trace_from_scene(
+                Vec3::new(height, 0.0, self.front_z() - 1.0),
+                Vec3::new(0.0, 0.0, 1.0),
+            )
+            .expect("RealisticCamera:paraxial ray from the scene was blocked");
+        let image = Self::cardinal_points(height, ori, dir);
+        let (ori, dir) = self
+            .trace_from_film(
+                Vec3::new(height, 0.0, self.rear_z() + 1.0),
+                Vec3::new(0.0, 0.0, -1.0),
+            )
+            .expect("RealisticCamera:paraxial ray from the film was blocked");
+        let object = Self::cardinal_points(height, ori, dir);
+        (object, image)
+    }
+    pub fn focal_length(&self) -> f64 {
+        let (_, (principal, focal)) = self.thick_lens();
+        focal - principal
+    }
+    pub fn focus(&mut self, focus_dist: f64) {
+        let object_z = -focus_dist / self.scale;
+        for _ in 0..8 {
+            let ((object_principal, _), (image_principal, image_focal)) = self.thick_lens();
+            let f = image_focal - image_principal;
+            let so = f64::max(object_principal - object_z, f * Self::CLOSEST_FOCUS);
+            let image_z = image_principal + so * f / (so - f);
+            self.elements.last_mut().unwrap().thickness += image_z;
+            if image_z.abs() < 1e-9 {
+                break;
+            }
+        }
+    }
+    fn compute_exit_pupils(&mut self) {
+        let rear_radius = self.elements.last().unwrap().aperture_radius * 1.5;
+        let rear_z = self.rear_z();
+        let half_diagonal =
+            0.5 * (self.film_width * self.film_width + self.film_height * self.film_height).sqrt();
+        let n = Self::PUPIL_SAMPLES;
+        let step = 2.0 * rear_radius / n as f64;
+        let mut reference = 0;
+        self.exit_pupils = (0..Self::PUPIL_BINS)
+            .map(|bin| {
+                let mut bounds = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+                for &r in [bin, bin + 1].iter() {
+                    let film =
+                        Vec3::new(r as f64 / Self::PUPIL_BINS as f64 * half_diagonal, 0.0, 0.0);
+                    for i in 0..n * n {
+                        let x = -rear_radius + ((i % n) as f64 + 0.5) * step;
+                        let y = -rear_radius + ((i / n) as f64 + 0.5) * step;
+                        if self
+                            .trace_from_film(film, Vec3::new(x, y, rear_z) - film)
+                            .is_some()
+                        {
+                            if r == 0 {
+                                reference += 1;
+                            }
+                            bounds = (
+                                bounds.0.min(x - step),
+                                bounds.1.min(y - step),
+                                bounds.2.max(x + step),
+                                bounds.3.max(y + step),
+                            );
+                        }
+                    }
+                }
+                bounds
+            })
+            .collect();
+        if reference == 0 {
+            panic!("RealisticCamera:no light passes through the lens");
+        }
+        self.reference_area = reference as f64 * step * step;
+    }
+    fn film_point(&self, u: f64, v: f64) -> Vec3 {
+        Vec3::new(
+            -(u - 0.5) * self.film_width,
+            -(v - 0.5) * self.film_height,
+            0.0,
+        )
+    }
+    fn trace_film_point(&self, film: Vec3, pupil: (f64, f64), time: f64) -> Option<(Ray, f64)> {
+        let r = (film.x * film.x + film.y * film.y).sqrt();
+        let (sin, cos) = if r > 0.0 {
+            (film.y / r, film.x / r)
+        } else {
+            (0.0, 1.0)
+        };
+        let rear = Vec3::new(
+            pupil.0 * cos - pupil.1 * sin,
+            pupil.0 * sin + pupil.1 * cos,
+            self.rear_z(),
+        );
+        let dir = rear - film;
+        let (ori, out) = self.trace_from_film(film, dir)?;
+        let cos_theta = dir.unit().z.abs();
+        let ray = Ray::new(
+            self.origin + (self.u * ori.x + self.v * ori.y + self.w * ori.z) * self.scale,
+            self.u * out.x + self.v * out.y + self.w * out.z,
+            time,
+        );
+        Some((ray, cos_theta.powi(4)))
+    }
+}
+impl CameraModel for RealisticCamera {
+    fn get_ray(&self, u: f64, v: f64, rng: &mut SmallRng) -> Option<Ray> {
+        let mut ray = self.get_ray_differential(u, v, 0.0, 0.0, rng)?;
+        ray.differential = None;
+        Some(ray)
+    }
+    fn get_ray_differential(
+        &self,
+        u: f64,
+        v: f64,
+        du: f64,
+        dv: f64,
+        rng: &mut SmallRng,
+    ) -> Option<Ray> {
+        self.sample_ray(u, v, du, dv, rng).map(|(ray, _)| ray)
+    }
+    fn sample_ray(
+        &self,
+        u: f64,
+        v: f64,
+        du: f64,
+        dv: f64,
+        rng: &mut SmallRng,
+    ) -> Option<(Ray, f64)> {
+        let film = self.film_point(u, v);
+        let half_diagonal =
+            0.5 * (self.film_width * self.film_width + self.film_height * self.film_height).sqrt();
+        let r = (film.x * film.x + film.y * film.y).sqrt() / half_diagonal;
+        let bin = ((r * Self::PUPIL_BINS as f64) as usize).min(Self::PUPIL_BINS - 1);
+        let (x0, y0, x1, y1) = self.exit_pupils[bin];
+        if x0 >= x1 || y0 >= y1 {
+            return None;
+        }
+        let pupil = (rng.gen_range(x0, x1), rng.gen_range(y0, y1));
+        let time = rng.gen_range(self.t1, self.t2);
+        let (mut ray, cos4) = self.trace_film_point(film, pupil, time)?;
+        let rx = self.trace_film_point(self.film_point(u + du, v), pupil, time);
+        let ry = self.trace_film_point(self.film_point(u, v + dv), pupil, time);
+        if let (Some((rx, _)), Some((ry, _))) = (rx, ry) {
+            ray.differential = Some(RayDifferential {
+                rx_ori: rx.ori,
+                rx_dir: rx.dir,
+                ry_ori: ry.ori,
+                ry_dir: ry.dir,
+            });
+        }
+        Some((ray, cos4 * (x1 - x0) * (y1 - y0) / self.reference_area))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_realistic_camera() {
+        let elements = load_lens("images/dgauss_50mm.lens");
+        assert_eq!(elements.len(), 11);
+        assert!(elements[5].is_stop() && elements[5].eta == 1.0);
+        let cam = RealisticCamera::new(
+            Vec3::zero(),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            elements,
+            8.0,
+            1.0,
+            43.27,
+            1.5,
+            0.001,
+            0.0,
+            1.0,
+        );
+        assert!((cam.focal_length() - 50.0).abs() < 2.0);
+        let mut close = cam.clone();
+        close.focus(0.01);
+        let back = |c: &RealisticCamera| c.elements.last().unwrap().thickness;
+        assert!(back(&close).is_finite() && back(&close) > back(&cam));
+
+        let mut rng = SmallRng::seed_from_u64(0);
+        let (mut center, mut corner, mut differentials) = (0.0, 0.0, 0);
+        for _ in 0..2000 {
+            if let Some((ray, weight)) = cam.sample_ray(0.5, 0.5, 0.001, 0.001, &mut rng) {
+                center += weight;
+                let t = (-1.0 - ray.ori.z) / ray.dir.z;
+                let p = ray.ori + ray.dir * t;
+                assert!(p.x.abs() < 1e-3 && p.y.abs() < 1e-3);
+                differentials += ray.differential.is_some() as usize;
+            }
+            if let Some((_, weight)) = cam.sample_ray(0.0, 0.0, 0.001, 0.001, &mut rng) {
+                corner += weight;
+            }
+        }
+        assert!(differentials > 1000);
+        let rays: Vec<Ray> = (0..100)
+            .filter_map(|_| cam.get_ray(0.0, 0.0, &mut rng))
+            .collect();
+        assert!(!rays.is_empty());
+        for ray in rays {
+            assert!(ray.ori.z < cam.front_z() * 0.001 * 0.5);
+            assert!(ray.dir.x < 0.0 && ray.dir.y < 0.0);
+        }
+        assert!((center / 2000.0 - 1.0).abs() < 0.1);
+        assert!(corner < center * 0.8);
+    }
+}
@@ -1,12 +1,24 @@
 mod bvh;
 mod camera;
+mod csg;
+mod environment;
+mod ies;
+mod lens;
+mod lights;
 mod materials;
+mod mesh;
 mod objects;
+mod patch;
 mod pdf;
+mod procedural;
 mod ray;
 pub mod ray_tracing;
 pub mod scenes;
+mod sdf;
+mod shapes;
+mod sky;
 mod texture;
+mod texture_graph;
 mod transforms;
 #[allow(clippy::float_cmp)]
 mod vec3;
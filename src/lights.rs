@@ -0,0 +1,503 @@
+use rand::{rngs::SmallRng, Rng};
+use std::sync::{Arc, OnceLock};
+
+pub use crate::objects::*;
+
+pub struct LightSample {
+    pub direction: Vec3,
+    pub distance: f64,
+    pub radiance: Vec3,
+}
+
+pub trait Light: Sync + Send {
+    fn power(&self) -> f64;
+    fn is_delta(&self) -> bool {
+        false
+    }
+    fn sample_delta(&self, _origin: Vec3, _rng: &mut SmallRng) -> Option<LightSample> {
+        None
+    }
+    fn pdf_value(&self, _origin: Vec3, _v: Vec3) -> f64 {
+        0.0
+    }
+    fn random(&self, _origin: Vec3, _rng: &mut SmallRng) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+}
+
+impl<T: Light + ?Sized> Light for Arc<T> {
+    fn power(&self) -> f64 {
+        (**self).power()
+    }
+    fn is_delta(&self) -> bool {
+        (**self).is_delta()
+    }
+    fn sample_delta(&self, origin: Vec3, rng: &mut SmallRng) -> Option<LightSample> {
+        (**self).sample_delta(origin, rng)
+    }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        (**self).pdf_value(origin, v)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        (**self).random(origin, rng)
+    }
+    fn bounding_box(&self) -> Option<Aabb> {
+        (**self).bounding_box()
+    }
+}
+
+pub fn emitter_power(emit: Vec3, area: f64) -> f64 {
+    emit.luminance() * area * std::f64::consts::PI
+}
+
+pub struct PointLight {
+    pub position: Vec3,
+    pub intensity: Vec3,
+}
+impl Light for PointLight {
+    fn power(&self) -> f64 {
+        4.0 * std::f64::consts::PI * self.intensity.luminance()
+    }
+    fn is_delta(&self) -> bool {
+        true
+    }
+    fn sample_delta(&self, origin: Vec3, _rng: &mut SmallRng) -> Option<LightSample> {
+        let direction = self.position - origin;
+        let distance = direction.length();
+        Some(LightSample {
+            direction: direction / distance,
+            distance,
+            radiance: self.intensity / (distance * distance),
+        })
+    }
+}
+
+pub struct DirectionalLight {
+    pub direction: Vec3,
+    pub radiance: Vec3,
+    pub scene_radius: f64,
+}
+impl DirectionalLight {
+    pub fn new(direction: Vec3, radiance: Vec3, scene_radius: f64) -> Self {
+        Self {
+            direction: direction.unit(),
+            radiance,
+            scene_radius,
+        }
+    }
+}
+impl Light for DirectionalLight {
+    fn power(&self) -> f64 {
+        std::f64::consts::PI * self.scene_radius * self.scene_radius * self.radiance.luminance()
+    }
+    fn is_delta(&self) -> bool {
+        true
+    }
+    fn sample_delta(&self, _origin: Vec3, _rng: &mut SmallRng) -> Option<LightSample> {
+        Some(LightSample {
+            direction: -self.direction,
+            distance: f64::INFINITY,
+            radiance: self.radiance,
+        })
+    }
+}
+
+pub struct AreaLight {
+    pub shape: Arc<dyn Object>,
+    pub power: f64,
+}
+impl Light for AreaLight {
+    fn power(&self) -> f64 {
+        self.power
+    }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        self.shape.pdf_value(origin, v)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        self.shape.random(origin, rng)
+    }
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.shape.bounding_box(0.0, 1.0)
+    }
+}
+
+pub struct AliasTable {
+    pub prob: Vec<f64>,
+    pub alias: Vec<usize>,
+    pub pdf: Vec<f64>,
+}
+impl AliasTable {
+    pub fn new(weights: &[f64]) -> Self {
+        let n = weights.len();
+        let total: f64 = weights.iter().map(|w| w.max(0.0)).sum();
+        let pdf: Vec<f64> = if total > 0.0 {
+            weights.iter().map(|w| w.max(0.0) / total).collect()
+        } else {
+            vec![1.0 / n as f64; n]
+        };
+        let mut prob = vec![1.0; n];
+        let mut alias: Vec<usize> = (0..n).collect();
+        let mut scaled: Vec<f64> = pdf.iter().map(|p| p * n as f64).collect();
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) =
+            (0..n).partition(|&i| scaled[i] < 1.0);
+        while let (Some(&s), Some(&l)) = (small.last(), large.last()) {
+            small.pop();
+            prob[s] = scaled[s];
+            alias[s] = l;
+            scaled[l] += scaled[s] - 1.0;
+            if scaled[l] < 1.0 {
+                large.pop();
+                small.push(l);
+            }
+        }
+        Self { prob, alias, pdf }
+    }
+    pub fn sample(&self, rng: &mut SmallRng) -> (usize, f64) {
+        let i = rng.gen_range(0, self.prob.len());
+        let i = if rng.gen::<f64>() < self.prob[i] {
+            i
+        } else {
+            self.alias[i]
+        };
+        (i, self.pdf[i])
+    }
+}
+
+pub struct LightBvhNode {
+    pub boxx: Option<Aabb>,
+    pub power: f64,
+    pub start: usize,
+    pub end: usize,
+    pub children: Option<(usize, usize)>,
+}
+impl LightBvhNode {
+    fn importance(&self, origin: Vec3) -> f64 {
+        match &self.boxx {
+            Some(boxx) => {
+                let center = (boxx.min + boxx.max) * 0.5;
+                let radius_squared = (boxx.max - boxx.min).squared_length() * 0.25;
+                let distance_squared = (center - origin).squared_length();
+                self.power / distance_squared.max(radius_squared)
+            }
+            None => self.power,
+        }
+    }
+}
+
+pub struct LightBvh {
+    pub nodes: Vec<LightBvhNode>,
+    pub order: Vec<usize>,
+    pub position: Vec<usize>,
+}
+impl LightBvh {
+    pub fn new(lights: &[Box<dyn Light>]) -> Self {
+        let mut items: Vec<(usize, Option<Aabb>, f64)> = lights
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (i, x.bounding_box(), x.power().max(0.0)))
+            .collect();
+        let mut bvh = Self {
+            nodes: vec![],
+            order: vec![],
+            position: vec![0; lights.len()],
+        };
+        if !items.is_empty() {
+            bvh.build(&mut items, 0);
+        }
+        for (pos, &i) in bvh.order.iter().enumerate() {
+            bvh.position[i] = pos;
+        }
+        bvh
+    }
+    fn build(&mut self, items: &mut [(usize, Option<Aabb>, f64)], start: usize) -> usize {
+        let index = self.nodes.len();
+        let power = items.iter().map(|x| x.2).sum();
+        let boxx = items
+            .iter()
+            .map(|x| x.1.clone())
+            .fold(None, |acc, x| match (acc, x) {
+                (None, x) => x,
+                (Some(a), Some(b)) => Some(Aabb::surrounding_box(a, b)),
+                (Some(a), None) => Some(a),
+            });
+        let unbounded = items.iter().any(|x| x.1.is_none());
+        self.nodes.push(LightBvhNode {
+            boxx: if unbounded { None } else { boxx.clone() },
+            power,
+            start,
+            end: start + items.len(),
+            children: None,
+        });
+        if items.len() == 1 {
+            self.order.push(items[0].0);
+            return index;
+        }
+        let centroid = |x: &Option<Aabb>| match x {
+            Some(b) => (b.min + b.max) * 0.5,
+            None => Vec3::zero(),
+        };
+        if let Some(b) = boxx {
+            let extent = b.max - b.min;
+            let axis = if extent.x > extent.y && extent.x > extent.z {
+                0
+            } else if extent.y > extent.z {
+                1
+            } else {
+                2
+            };
+            let key = |x: &Option<Aabb>| {
+                let c = centroid(x);
+                match axis {
+                    0 => c.x,
+                    1 => c.y,
+                    _ => c.z,
+                }
+            };
+            items.sort_by(|a, b| {
+                key(&a.1)
+                    .partial_cmp(&key(&b.1))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+        }
+        let mid = items.len() / 2;
+        let (left_items, right_items) = items.split_at_mut(mid);
+        let left = self.build(left_items, start);
+        let right = self.build(right_items, start + mid);
+        self.nodes[index].children = Some((left, right));
+        index
+    }
+    fn split(&self, origin: Vec3, left: usize, right: usize) -> f64 {
+        let importance_left = self.nodes[left].importance(origin);
+        let importance_right = self.nodes[right].importance(origin);
+        if importance_left + importance_right > 0.0 {
+            importance_left / (importance_left + importance_right)
+        } else {
+            0.5
+        }
+    }
+    pub fn sample(&self, origin: Vec3, rng: &mut SmallRng) -> (usize, f64) {
+        let mut node = 0;
+        let mut prob = 1.0;
+        while let Some((left, right)) = self.nodes[node].children {
+            let p = self.split(origin, left, right);
+            if rng.gen::<f64>() < p {
+                node = left;
+                prob *= p;
+            } else {
+                node = right;
+                prob *= 1.0 - p;
+            }
+        }
+        (self.order[self.nodes[node].start], prob)
+    }
+    pub fn hit_lights(&self, ray: &Ray) -> Vec<usize> {
+        let mut ans = vec![];
+        let mut stack = if self.nodes.is_empty() {
+            vec![]
+        } else {
+            vec![0]
+        };
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if let Some(boxx) = &node.boxx {
+                if !boxx.hit(ray, 0.001, f64::MAX) {
+                    continue;
+                }
+            }
+            match node.children {
+                Some((left, right)) => stack.extend([left, right].iter()),
+                None => ans.push(self.order[node.start]),
+            }
+        }
+        ans
+    }
+    pub fn probability(&self, origin: Vec3, light: usize) -> f64 {
+        let pos = self.position[light];
+        let mut node = 0;
+        let mut prob = 1.0;
+        while let Some((left, right)) = self.nodes[node].children {
+            let p = self.split(origin, left, right);
+            if pos < self.nodes[left].end {
+                node = left;
+                prob *= p;
+            } else {
+                node = right;
+                prob *= 1.0 - p;
+            }
+        }
+        prob
+    }
+}
+
+pub struct LightList {
+    pub area: Vec<Box<dyn Light>>,
+    pub delta: Vec<Box<dyn Light>>,
+    pub importance: bool,
+    area_table: OnceLock<AliasTable>,
+    delta_table: OnceLock<AliasTable>,
+    area_bvh: OnceLock<LightBvh>,
+}
+impl Default for LightList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl LightList {
+    pub fn new() -> Self {
+        Self {
+            area: vec![],
+            delta: vec![],
+            importance: false,
+            area_table: OnceLock::new(),
+            delta_table: OnceLock::new(),
+            area_bvh: OnceLock::new(),
+        }
+    }
+    fn table(lights: &[Box<dyn Light>]) -> AliasTable {
+        AliasTable::new(&lights.iter().map(|x| x.power()).collect::<Vec<f64>>())
+    }
+    pub fn add(&mut self, light: Box<dyn Light>) {
+        if light.is_delta() {
+            self.delta.push(light);
+            self.delta_table = OnceLock::new();
+        } else {
+            self.area.push(light);
+            self.area_table = OnceLock::new();
+            self.area_bvh = OnceLock::new();
+        }
+    }
+    pub fn use_bvh(&mut self) {
+        self.importance = true;
+    }
+    pub fn area_table(&self) -> &AliasTable {
+        self.area_table.get_or_init(|| Self::table(&self.area))
+    }
+    pub fn delta_table(&self) -> &AliasTable {
+        self.delta_table.get_or_init(|| Self::table(&self.delta))
+    }
+    pub fn area_bvh(&self) -> &LightBvh {
+        self.area_bvh.get_or_init(|| LightBvh::new(&self.area))
+    }
+    fn area_probability(&self, origin: Vec3, light: usize) -> f64 {
+        if self.importance {
+            self.area_bvh().probability(origin, light)
+        } else {
+            self.area_table().pdf[light]
+        }
+    }
+    pub fn add_area<T: Object + 'static>(&mut self, world: &mut ObjectList, shape: T, power: f64) {
+        let shape = Arc::new(shape);
+        world.add(Box::new(shape.clone()));
+        self.add(Box::new(AreaLight { shape, power }));
+    }
+    pub fn add_sphere(&mut self, world: &mut ObjectList, center: Vec3, radius: f64, emit: Vec3) {
+        self.add_area(
+            world,
+            Sphere {
+                center,
+                radius,
+                material: DiffuseLight {
+                    emit: SolidColor { color: emit },
+                },
+            },
+            emitter_power(emit, 4.0 * std::f64::consts::PI * radius * radius),
+        );
+    }
+    pub fn has_area(&self) -> bool {
+        !self.area.is_empty()
+    }
+    pub fn sample_delta(&self, origin: Vec3, rng: &mut SmallRng) -> Option<(LightSample, f64)> {
+        if self.delta.is_empty() {
+            return None;
+        }
+        let (i, prob) = self.delta_table().sample(rng);
+        self.delta[i]
+            .sample_delta(origin, rng)
+            .map(|sample| (sample, prob))
+    }
+}
+impl Object for LightList {
+    fn hit(&self, _ray: &Ray, _t_min: f64, _t_max: f64) -> Option<HitRecord> {
+        None
+    }
+    fn bounding_box(&self, _t1: f64, _t2: f64) -> Option<Aabb> {
+        None
+    }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        let ray = Ray::new(origin, v, 0.0);
+        self.area_bvh()
+            .hit_lights(&ray)
+            .into_iter()
+            .map(|i| match self.area[i].pdf_value(origin, v) {
+                pdf if pdf > 0.0 => self.area_probability(origin, i) * pdf,
+                _ => 0.0,
+            })
+            .sum()
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        let (i, _) = if self.importance {
+            self.area_bvh().sample(origin, rng)
+        } else {
+            self.area_table().sample(rng)
+        };
+        self.area[i].random(origin, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_alias_table() {
+        let weights = [1.0, 0.0, 3.0, 6.0];
+        let table = AliasTable::new(&weights);
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut count = [0usize; 4];
+        let n = 200000;
+        for _ in 0..n {
+            let (i, pdf) = table.sample(&mut rng);
+            assert!((pdf - weights[i] / 10.0).abs() < 1e-12);
+            count[i] += 1;
+        }
+        for i in 0..4 {
+            assert!((count[i] as f64 / n as f64 - weights[i] / 10.0).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_light_bvh_probability() {
+        let mut world = ObjectList { objects: vec![] };
+        let mut lights = LightList::new();
+        for i in 0..7 {
+            lights.add_sphere(
+                &mut world,
+                Vec3::new(i as f64 * 3.0, 1.0, (i % 3) as f64),
+                0.5,
+                Vec3::new(1.0 + i as f64, 1.0, 1.0),
+            );
+        }
+        lights.use_bvh();
+        let bvh = lights.area_bvh();
+        let origin = Vec3::new(2.0, 0.0, 0.0);
+        let total: f64 = (0..7).map(|i| bvh.probability(origin, i)).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        let mut rng = SmallRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let (i, prob) = bvh.sample(origin, &mut rng);
+            assert!((prob - bvh.probability(origin, i)).abs() < 1e-12);
+        }
+        for _ in 0..100 {
+            let v = lights.random(origin, &mut rng);
+            let expected: f64 = (0..7)
+                .map(|i| bvh.probability(origin, i) * lights.area[i].pdf_value(origin, v))
+                .sum();
+            assert!(expected > 0.0);
+            assert!((lights.pdf_value(origin, v) - expected).abs() < 1e-12 * expected);
+        }
+    }
+}
@@ -1,14 +1,39 @@
+#[allow(dead_code)]
 mod camera;
+#[allow(dead_code)]
+mod csg;
+mod environment;
 //mod image_test;
 mod bvh;
+mod ies;
+#[allow(dead_code)]
+mod lens;
+#[allow(dead_code)]
+mod lights;
 mod materials;
+#[allow(dead_code)]
+mod mesh;
 mod objects;
+#[allow(dead_code)]
+mod patch;
+#[allow(dead_code)]
 mod pdf;
+#[allow(dead_code)]
+mod procedural;
 mod ray;
 mod ray_tracing;
 #[allow(dead_code)]
 mod scenes;
+#[allow(dead_code)]
+mod sdf;
+#[allow(dead_code)]
+mod shapes;
+#[allow(dead_code)]
+mod sky;
+#[allow(dead_code)]
 mod texture;
+#[allow(dead_code)]
+mod texture_graph;
 mod transforms;
 #[allow(clippy::float_cmp, dead_code)]
 mod vec3;
@@ -1,7 +1,10 @@
 use rand::{rngs::SmallRng, Rng};
 
+pub use crate::ies::*;
 pub use crate::objects::*;
+pub use crate::procedural::*;
 pub use crate::texture::*;
+pub use crate::texture_graph::*;
 
 fn schlick(cosine: f64, ref_idx: f64) -> f64 {
     let r0 = ((1.0 - ref_idx) / (1.0 + ref_idx)) * ((1.0 - ref_idx) / (1.0 + ref_idx));
@@ -17,15 +20,22 @@ pub enum ScatterRecord {
         attenuation: Vec3,
         pdf: CosinePDF,
     },
+    Volume {
+        attenuation: Vec3,
+        pdf: PhasePDF,
+    },
 }
 pub trait Material: Sync + Send {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<ScatterRecord>;
-    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord, _u: f64, _v: f64, _p: Vec3) -> Vec3 {
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord) -> Vec3 {
         Vec3::zero()
     }
     fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
         0.0
     }
+    fn opacity(&self, _rec: &HitRecord) -> f64 {
+        1.0
+    }
 }
 
 #[derive(Clone)]
@@ -35,7 +45,7 @@ pub struct Lambertian<T: Texture> {
 impl<T: Texture> Material for Lambertian<T> {
     fn scatter(&self, _r_in: &Ray, rec: &HitRecord, _rng: &mut SmallRng) -> Option<ScatterRecord> {
         Some(ScatterRecord::Diffuse {
-            attenuation: self.albedo.value(rec.u, rec.v, rec.p),
+            attenuation: self.albedo.value_at(rec),
             pdf: CosinePDF::new(rec.normal),
         })
     }
@@ -56,20 +66,42 @@ pub struct Metal {
 }
 impl Material for Metal {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<ScatterRecord> {
-        let reflected = reflect(r_in.dir.unit(), rec.normal);
-        let scattered = Ray::new(
-            rec.p,
-            reflected + random_in_unit_sphere(rng) * self.fuzz,
-            r_in.time,
-        );
-        if scattered.dir * rec.normal > 0.0 {
-            Some(ScatterRecord::Specular {
-                attenuation: self.albedo,
-                specular_ray: scattered,
-            })
-        } else {
-            None
-        }
+        metal_scatter(r_in, rec, self.albedo, self.fuzz, rng)
+    }
+}
+
+#[derive(Clone)]
+pub struct RoughMetal<T: Texture> {
+    pub albedo: Vec3,
+    pub fuzz: T,
+}
+impl<T: Texture> Material for RoughMetal<T> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<ScatterRecord> {
+        let fuzz = num::clamp(self.fuzz.value_at(rec).luminance(), 0.0, 1.0);
+        metal_scatter(r_in, rec, self.albedo, fuzz, rng)
+    }
+}
+
+fn metal_scatter(
+    r_in: &Ray,
+    rec: &HitRecord,
+    albedo: Vec3,
+    fuzz: f64,
+    rng: &mut SmallRng,
+) -> Option<ScatterRecord> {
+    let reflected = reflect(r_in.dir.unit(), rec.normal);
+    let scattered = Ray::new(
+        rec.p,
+        reflected + random_in_unit_sphere(rng) * fuzz,
+        r_in.time,
+    );
+    if scattered.dir * rec.normal > 0.0 {
+        Some(ScatterRecord::Specular {
+            attenuation: albedo,
+            specular_ray: scattered,
+        })
+    } else {
+        None
     }
 }
 
@@ -142,6 +174,45 @@ impl Material for FrostedDielectric {
     }
 }
 
+#[derive(Clone)]
+pub struct SubsurfaceBoundary {
+    pub ref_idx: f64,
+}
+impl Material for SubsurfaceBoundary {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<ScatterRecord> {
+        if r_in.dir * rec.normal > 0.0 {
+            return Some(ScatterRecord::Diffuse {
+                attenuation: Vec3::ones(),
+                pdf: CosinePDF::new(rec.normal),
+            });
+        }
+        let cos_theta = (-r_in.dir.unit() * rec.normal).min(1.0);
+        if rng.gen::<f64>() < schlick(cos_theta, self.ref_idx) {
+            return Some(ScatterRecord::Specular {
+                attenuation: Vec3::ones(),
+                specular_ray: Ray::new(rec.p, reflect(r_in.dir.unit(), rec.normal), r_in.time),
+            });
+        }
+        Some(ScatterRecord::Diffuse {
+            attenuation: Vec3::ones(),
+            pdf: CosinePDF::new(-rec.normal),
+        })
+    }
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let normal = if r_in.dir * rec.normal > 0.0 {
+            rec.normal
+        } else {
+            -rec.normal
+        };
+        let cosine = normal * scattered.dir.unit();
+        if cosine > 0.0 {
+            cosine / std::f64::consts::PI
+        } else {
+            0.0
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct DiffuseLight<T: Texture> {
     pub emit: T,
@@ -150,24 +221,417 @@ impl<T: Texture> Material for DiffuseLight<T> {
     fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _rng: &mut SmallRng) -> Option<ScatterRecord> {
         None
     }
-    fn emitted(&self, r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: Vec3) -> Vec3 {
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
         if r_in.dir * rec.normal < 0.0 {
-            self.emit.value(u, v, p)
+            self.emit.value_at(rec)
         } else {
             Vec3::zero()
         }
     }
 }
 
+#[derive(Clone)]
+pub struct DiffuseLightTwoSided<T: Texture> {
+    pub emit: T,
+}
+impl<T: Texture> Material for DiffuseLightTwoSided<T> {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _rng: &mut SmallRng) -> Option<ScatterRecord> {
+        None
+    }
+    fn emitted(&self, _r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.emit.value_at(rec)
+    }
+}
+
+#[derive(Clone)]
+pub struct SpotLight<T: Texture> {
+    pub emit: T,
+    pub direction: Vec3,
+    pub cos_total_width: f64,
+    pub cos_falloff_start: f64,
+}
+impl<T: Texture> SpotLight<T> {
+    pub fn new(emit: T, direction: Vec3, total_width: f64, falloff_start: f64) -> Self {
+        Self {
+            emit,
+            direction: direction.unit(),
+            cos_total_width: (total_width * std::f64::consts::PI / 180.0).cos(),
+            cos_falloff_start: (falloff_start * std::f64::consts::PI / 180.0).cos(),
+        }
+    }
+    fn falloff(&self, cos_theta: f64) -> f64 {
+        if cos_theta < self.cos_total_width {
+            return 0.0;
+        }
+        if cos_theta >= self.cos_falloff_start {
+            return 1.0;
+        }
+        let delta =
+            (cos_theta - self.cos_total_width) / (self.cos_falloff_start - self.cos_total_width);
+        delta * delta * delta * delta
+    }
+}
+impl<T: Texture> Material for SpotLight<T> {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _rng: &mut SmallRng) -> Option<ScatterRecord> {
+        None
+    }
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        if r_in.dir * rec.normal < 0.0 {
+            self.emit.value_at(rec) * self.falloff(-r_in.dir.unit() * self.direction)
+        } else {
+            Vec3::zero()
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct IesLight<T: Texture> {
+    pub emit: T,
+    pub profile: IesProfile,
+    pub uvw: ONB,
+}
+impl<T: Texture> IesLight<T> {
+    pub fn new(emit: T, profile: IesProfile, direction: Vec3) -> Self {
+        Self {
+            emit,
+            profile,
+            uvw: ONB::build_from_w(direction),
+        }
+    }
+}
+impl<T: Texture> Material for IesLight<T> {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _rng: &mut SmallRng) -> Option<ScatterRecord> {
+        None
+    }
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        if r_in.dir * rec.normal >= 0.0 || self.profile.max_candela <= 0.0 {
+            return Vec3::zero();
+        }
+        let d = -r_in.dir.unit();
+        let vertical = num::clamp(d * self.uvw.w, -1.0, 1.0).acos().to_degrees();
+        let horizontal = (d * self.uvw.v).atan2(d * self.uvw.u).to_degrees();
+        self.emit.value_at(rec)
+            * (self.profile.value(vertical, horizontal) / self.profile.max_candela)
+    }
+}
+
 #[derive(Clone)]
 pub struct Isotropic<T: Texture> {
     pub albedo: T,
 }
 impl<T: Texture> Material for Isotropic<T> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, _rng: &mut SmallRng) -> Option<ScatterRecord> {
+        Some(ScatterRecord::Volume {
+            attenuation: self.albedo.value_at(rec),
+            pdf: PhasePDF::new(Phase::Isotropic, r_in.dir),
+        })
+    }
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        1.0 / (4.0 * std::f64::consts::PI)
+    }
+}
+
+#[derive(Clone)]
+pub struct PhaseFunction<T: Texture> {
+    pub albedo: T,
+    pub phase: Phase,
+}
+impl<T: Texture> Material for PhaseFunction<T> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, _rng: &mut SmallRng) -> Option<ScatterRecord> {
+        Some(ScatterRecord::Volume {
+            attenuation: self.albedo.value_at(rec),
+            pdf: PhasePDF::new(self.phase, r_in.dir),
+        })
+    }
+    fn scattering_pdf(&self, r_in: &Ray, _rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.phase.value(r_in.dir.unit() * scattered.dir.unit())
+    }
+}
+
+#[derive(Clone)]
+pub struct RandomWalk<T: Texture> {
+    pub albedo: T,
+    pub phase: Phase,
+}
+impl<T: Texture> Material for RandomWalk<T> {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<ScatterRecord> {
         Some(ScatterRecord::Specular {
-            attenuation: self.albedo.value(rec.u, rec.v, rec.p),
-            specular_ray: Ray::new(rec.p, random_in_unit_sphere(rng), r_in.time),
+            attenuation: self.albedo.value_at(rec),
+            specular_ray: Ray::new(
+                rec.p,
+                PhasePDF::new(self.phase, r_in.dir).generate(rng),
+                r_in.time,
+            ),
         })
     }
 }
+
+#[derive(Clone)]
+pub struct Absorber;
+impl Material for Absorber {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _rng: &mut SmallRng) -> Option<ScatterRecord> {
+        None
+    }
+}
+
+#[derive(Clone)]
+pub struct NormalMap<M: Material, T: Texture> {
+    pub material: M,
+    pub map: T,
+    pub strength: f64,
+}
+impl<M: Material, T: Texture> NormalMap<M, T> {
+    fn shade<'a>(&self, rec: &HitRecord<'a>) -> HitRecord<'a> {
+        let c = self.map.value_at(rec) * 2.0 - Vec3::ones();
+        let bitangent = Vec3::cross(rec.normal, rec.tangent);
+        let normal = rec.tangent * (c.x * self.strength)
+            + bitangent * (c.y * self.strength)
+            + rec.normal * c.z;
+        if normal.squared_length() <= 0.0 {
+            return rec.clone();
+        }
+        rec.clone().with_shading_normal(normal.unit())
+    }
+}
+impl<M: Material, T: Texture> Material for NormalMap<M, T> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<ScatterRecord> {
+        self.material.scatter(r_in, &self.shade(rec), rng)
+    }
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.material.emitted(r_in, &self.shade(rec))
+    }
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.material
+            .scattering_pdf(r_in, &self.shade(rec), scattered)
+    }
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        self.material.opacity(rec)
+    }
+}
+
+#[derive(Clone)]
+pub struct BumpMap<M: Material, T: Texture> {
+    pub material: M,
+    pub height: T,
+    pub scale: f64,
+}
+impl<M: Material, T: Texture> BumpMap<M, T> {
+    fn shade<'a>(&self, rec: &HitRecord<'a>) -> HitRecord<'a> {
+        let delta = 1e-3;
+        let height = |u: f64, v: f64, p: Vec3| {
+            self.height
+                .value_at(&HitRecord {
+                    u,
+                    v,
+                    p,
+                    ..rec.clone()
+                })
+                .luminance()
+        };
+        let h = height(rec.u, rec.v, rec.p);
+        let dhdu = (height(rec.u + delta, rec.v, rec.p + rec.dpdu * delta) - h) / delta;
+        let dhdv = (height(rec.u, rec.v + delta, rec.p + rec.dpdv * delta) - h) / delta;
+        let dpdu = rec.dpdu + rec.normal * (dhdu * self.scale);
+        let dpdv = rec.dpdv + rec.normal * (dhdv * self.scale);
+        let normal = Vec3::cross(dpdu, dpdv);
+        if normal.squared_length() <= 0.0 {
+            return rec.clone();
+        }
+        let normal = normal.unit();
+        if normal * rec.normal < 0.0 {
+            rec.clone().with_shading_normal(-normal)
+        } else {
+            rec.clone().with_shading_normal(normal)
+        }
+    }
+}
+impl<M: Material, T: Texture> Material for BumpMap<M, T> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<ScatterRecord> {
+        self.material.scatter(r_in, &self.shade(rec), rng)
+    }
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.material.emitted(r_in, &self.shade(rec))
+    }
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.material
+            .scattering_pdf(r_in, &self.shade(rec), scattered)
+    }
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        self.material.opacity(rec)
+    }
+}
+
+#[derive(Clone)]
+pub struct OpacityMask<M: Material, T: Texture> {
+    pub material: M,
+    pub opacity: T,
+}
+impl<M: Material, T: Texture> Material for OpacityMask<M, T> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<ScatterRecord> {
+        self.material.scatter(r_in, rec, rng)
+    }
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Vec3 {
+        self.material.emitted(r_in, rec)
+    }
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.material.scattering_pdf(r_in, rec, scattered)
+    }
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        self.material.opacity(rec) * self.opacity.value_at(rec).luminance()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_normal_and_bump_map() {
+        let base = Lambertian {
+            albedo: SolidColor {
+                color: Vec3::ones(),
+            },
+        };
+        let rec = HitRecord {
+            t: 1.0,
+            p: Vec3::new(0.3, 0.0, 0.2),
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            geometric_normal: Vec3::new(0.0, 1.0, 0.0),
+            tangent: Vec3::new(1.0, 0.0, 0.0),
+            dpdu: Vec3::new(1.0, 0.0, 0.0),
+            dpdv: Vec3::new(0.0, 0.0, -1.0),
+            front_face: true,
+            uv_footprint: (0.0, 0.0),
+            mat_ptr: &base,
+            u: 0.5,
+            v: 0.5,
+        };
+        let flat = NormalMap {
+            material: base.clone(),
+            map: SolidColor {
+                color: Vec3::new(0.5, 0.5, 1.0),
+            },
+            strength: 1.0,
+        };
+        assert!((flat.shade(&rec).normal - rec.normal).length() < 1e-9);
+        let tilted = NormalMap {
+            material: base.clone(),
+            map: SolidColor {
+                color: Vec3::new(1.0, 0.5, 1.0),
+            },
+            strength: 1.0,
+        };
+        let shaded = tilted.shade(&rec);
+        assert!((shaded.normal - Vec3::new(1.0, 1.0, 0.0).unit()).length() < 1e-9);
+        assert!((shaded.normal * shaded.tangent).abs() < 1e-9);
+
+        let bump = BumpMap {
+            material: base.clone(),
+            height: SolidColor {
+                color: Vec3::ones(),
+            },
+            scale: 1.0,
+        };
+        assert!((bump.shade(&rec).normal - rec.normal).length() < 1e-9);
+    }
+
+    #[test]
+    fn test_rough_metal() {
+        let ray = Ray::new(Vec3::new(-1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0), 0.0);
+        let metal = Metal {
+            albedo: Vec3::ones(),
+            fuzz: 0.4,
+        };
+        let rough = RoughMetal {
+            albedo: Vec3::ones(),
+            fuzz: SolidColor {
+                color: Vec3::ones() * 0.4,
+            },
+        };
+        let rec = HitRecord::new(
+            &ray,
+            1.0,
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            &rough,
+            0.5,
+            0.5,
+        );
+        let specular = |material: &dyn Material, seed: u64| match material.scatter(
+            &ray,
+            &rec,
+            &mut SmallRng::seed_from_u64(seed),
+        ) {
+            Some(ScatterRecord::Specular { specular_ray, .. }) => Some(specular_ray.dir),
+            _ => None,
+        };
+        for seed in 0..20 {
+            assert_eq!(specular(&metal, seed), specular(&rough, seed));
+        }
+    }
+
+    #[test]
+    fn test_opacity_mask() {
+        let leaf = |opacity: f64| -> Box<dyn Object> {
+            Box::new(Quad {
+                q: Vec3::new(-1.0, -1.0, 0.0),
+                u: Vec3::new(2.0, 0.0, 0.0),
+                v: Vec3::new(0.0, 2.0, 0.0),
+                material: OpacityMask {
+                    material: Lambertian {
+                        albedo: SolidColor {
+                            color: Vec3::ones(),
+                        },
+                    },
+                    opacity: SolidColor {
+                        color: Vec3::ones() * opacity,
+                    },
+                },
+            })
+        };
+        let wall = || -> Box<dyn Object> {
+            Box::new(Sphere {
+                center: Vec3::new(0.0, 0.0, -3.0),
+                radius: 1.0,
+                material: Dielectric { ref_idx: 1.5 },
+            })
+        };
+        let mut rng = SmallRng::seed_from_u64(0);
+        for &opacity in [0.2, 0.8].iter() {
+            let world = ObjectList {
+                objects: vec![leaf(opacity), wall()],
+            };
+            let bvh = Bvh::new(&mut vec![leaf(opacity), wall()], 0.0, 1.0);
+            let (mut world_hits, mut bvh_hits) = (0, 0);
+            let n = 10000;
+            for _ in 0..n {
+                let ori = Vec3::new(rng.gen_range(-0.1, 0.1), rng.gen_range(-0.1, 0.1), 5.0);
+                let ray = Ray::new(ori, Vec3::new(0.0, 0.0, -1.0), 0.0);
+                let t = world.hit(&ray, 0.001, f64::MAX).unwrap().t;
+                assert!((t - 5.0).abs() < 1e-6 || (t - 7.0).abs() < 0.1);
+                world_hits += ((t - 5.0).abs() < 1e-6) as usize;
+                let t = bvh.hit(&ray, 0.001, f64::MAX).unwrap().t;
+                bvh_hits += ((t - 5.0).abs() < 1e-6) as usize;
+            }
+            assert!((world_hits as f64 / n as f64 - opacity).abs() < 0.02);
+            assert!((bvh_hits as f64 / n as f64 - opacity).abs() < 0.02);
+        }
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let world = ObjectList {
+            objects: vec![leaf(1.0)],
+        };
+        assert!((world.hit(&ray, 0.001, f64::MAX).unwrap().t - 5.0).abs() < 1e-6);
+        let world = ObjectList {
+            objects: vec![leaf(0.0)],
+        };
+        assert!(world.hit(&ray, 0.001, f64::MAX).is_none());
+        let world = ObjectList {
+            objects: vec![leaf(0.0), wall()],
+        };
+        let bvh = Bvh::new(&mut vec![leaf(0.0), wall()], 0.0, 1.0);
+        assert_eq!(world.hit_all(&ray).len(), 3);
+        assert_eq!(bvh.hit_all(&ray).len(), 3);
+    }
+}
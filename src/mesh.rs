@@ -0,0 +1,472 @@
+use rand::{rngs::SmallRng, Rng};
+
+pub use crate::objects::*;
+
+fn triangle_intersect(
+    a: Vec3,
+    b: Vec3,
+    c: Vec3,
+    ray: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64, f64)> {
+    let e1 = b - a;
+    let e2 = c - a;
+    let pvec = Vec3::cross(ray.dir, e2);
+    let det = e1 * pvec;
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let tvec = ray.ori - a;
+    let u = tvec * pvec * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let qvec = Vec3::cross(tvec, e1);
+    let v = ray.dir * qvec * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = e2 * qvec * inv_det;
+    if t < t_min || t > t_max {
+        return None;
+    }
+    Some((t, u, v))
+}
+fn triangle_box(a: Vec3, b: Vec3, c: Vec3) -> Aabb {
+    let delta = 0.0001;
+    Aabb {
+        min: Vec3::new(
+            a.x.min(b.x).min(c.x) - delta,
+            a.y.min(b.y).min(c.y) - delta,
+            a.z.min(b.z).min(c.z) - delta,
+        ),
+        max: Vec3::new(
+            a.x.max(b.x).max(c.x) + delta,
+            a.y.max(b.y).max(c.y) + delta,
+            a.z.max(b.z).max(c.z) + delta,
+        ),
+    }
+}
+fn triangle_sample(a: Vec3, b: Vec3, c: Vec3, rng: &mut SmallRng) -> Vec3 {
+    let s = rng.gen::<f64>().sqrt();
+    let b0 = 1.0 - s;
+    let b1 = rng.gen::<f64>() * s;
+    a * b0 + b * b1 + c * (1.0 - b0 - b1)
+}
+
+pub struct Triangle<T: Material> {
+    pub a: Vec3,
+    pub b: Vec3,
+    pub c: Vec3,
+    pub material: T,
+}
+impl<T: Material> Object for Triangle<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (t, u, v) = triangle_intersect(self.a, self.b, self.c, ray, t_min, t_max)?;
+        Some(HitRecord::new(
+            ray,
+            t,
+            Vec3::cross(self.b - self.a, self.c - self.a).unit(),
+            self.b - self.a,
+            self.c - self.a,
+            &self.material,
+            u,
+            v,
+        ))
+    }
+    fn bounding_box(&self, _t1: f64, _t2: f64) -> Option<Aabb> {
+        Some(triangle_box(self.a, self.b, self.c))
+    }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        match self.hit(&Ray::new(origin, v, 0.0), 0.001, f64::MAX) {
+            Some(rec) => {
+                let area = Vec3::cross(self.b - self.a, self.c - self.a).length() / 2.0;
+                let distance_squared = rec.t * rec.t * v.squared_length();
+                let cosine = (v * rec.normal).abs() / v.length();
+                distance_squared / (cosine * area)
+            }
+            None => 0.0,
+        }
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        triangle_sample(self.a, self.b, self.c, rng) - origin
+    }
+}
+
+pub struct MeshNode {
+    pub boxx: Aabb,
+    pub start: usize,
+    pub end: usize,
+    pub children: Option<(usize, usize)>,
+}
+
+pub fn read_obj(filename: &str) -> (Vec<Vec3>, Vec<Vec<usize>>) {
+    let content = std::fs::read_to_string(filename).expect("Mesh:failed to open file");
+    let mut vertices = vec![];
+    let mut faces = vec![];
+    for line in content.lines() {
+        let mut items = line.split_whitespace();
+        match items.next() {
+            Some("v") => {
+                let v: Vec<f64> = items
+                    .take(3)
+                    .map(|x| x.parse().expect("Mesh:invalid vertex"))
+                    .collect();
+                vertices.push(Vec3::new(v[0], v[1], v[2]));
+            }
+            Some("f") => {
+                let index: Vec<usize> = items
+                    .map(|x| {
+                        let i: i64 = x
+                            .split('/')
+                            .next()
+                            .unwrap_or("")
+                            .parse()
+                            .expect("Mesh:invalid face");
+                        if i < 0 {
+                            (vertices.len() as i64 + i) as usize
+                        } else {
+                            (i - 1) as usize
+                        }
+                    })
+                    .collect();
+                faces.push(index);
+            }
+            _ => {}
+        }
+    }
+    (vertices, faces)
+}
+
+pub struct Mesh<T: Material> {
+    pub vertices: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f64, f64)>,
+    pub faces: Vec<[usize; 3]>,
+    pub material: T,
+    pub nodes: Vec<MeshNode>,
+    pub area_table: AliasTable,
+    pub area: f64,
+}
+impl<T: Material> Mesh<T> {
+    pub fn new(vertices: Vec<Vec3>, faces: Vec<[usize; 3]>, material: T) -> Self {
+        Self::with_attributes(vertices, vec![], vec![], faces, material)
+    }
+    pub fn with_attributes(
+        vertices: Vec<Vec3>,
+        normals: Vec<Vec3>,
+        uvs: Vec<(f64, f64)>,
+        faces: Vec<[usize; 3]>,
+        material: T,
+    ) -> Self {
+        let mut mesh = Self {
+            vertices,
+            normals,
+            uvs,
+            faces,
+            material,
+            nodes: vec![],
+            area_table: AliasTable::new(&[]),
+            area: 0.0,
+        };
+        let len = mesh.faces.len();
+        if len > 0 {
+            mesh.build(0, len);
+        }
+        let areas: Vec<f64> = (0..len)
+            .map(|i| {
+                let (a, b, c) = mesh.triangle(i);
+                Vec3::cross(b - a, c - a).length() / 2.0
+            })
+            .collect();
+        mesh.area = areas.iter().sum();
+        mesh.area_table = AliasTable::new(&areas);
+        mesh
+    }
+    pub fn load_obj(filename: &str, material: T) -> Self {
+        let (vertices, polygons) = read_obj(filename);
+        let mut faces = vec![];
+        for index in polygons {
+            for k in 1..index.len().saturating_sub(1) {
+                faces.push([index[0], index[k], index[k + 1]]);
+            }
+        }
+        Self::new(vertices, faces, material)
+    }
+    pub fn transformed(self, scale: f64, offset: Vec3) -> Self {
+        let vertices = self.vertices.iter().map(|&x| x * scale + offset).collect();
+        Self::with_attributes(vertices, self.normals, self.uvs, self.faces, self.material)
+    }
+    pub fn smooth_normals(self) -> Self {
+        let mut normals = vec![Vec3::zero(); self.vertices.len()];
+        for face in self.faces.iter() {
+            let [a, b, c] = *face;
+            let n = Vec3::cross(
+                self.vertices[b] - self.vertices[a],
+                self.vertices[c] - self.vertices[a],
+            );
+            for &i in face.iter() {
+                normals[i] += n;
+            }
+        }
+        let normals = normals
+            .iter()
+            .map(|&n| {
+                if n.squared_length() > 0.0 {
+                    n.unit()
+                } else {
+                    n
+                }
+            })
+            .collect();
+        Self::with_attributes(self.vertices, normals, self.uvs, self.faces, self.material)
+    }
+    pub fn displaced<D: Texture>(self, height: &D, scale: f64) -> Self {
+        let mesh = if self.normals.is_empty() {
+            self.smooth_normals()
+        } else {
+            self
+        };
+        let vertices = mesh
+            .vertices
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| {
+                let (u, v) = mesh.uvs.get(i).cloned().unwrap_or((0.0, 0.0));
+                p + mesh.normals[i] * (height.value(u, v, p).luminance() * scale)
+            })
+            .collect();
+        Self::with_attributes(vertices, vec![], mesh.uvs, mesh.faces, mesh.material)
+            .smooth_normals()
+    }
+    fn triangle(&self, i: usize) -> (Vec3, Vec3, Vec3) {
+        let [a, b, c] = self.faces[i];
+        (self.vertices[a], self.vertices[b], self.vertices[c])
+    }
+    fn build(&mut self, start: usize, end: usize) -> usize {
+        let mut boxx = {
+            let (a, b, c) = self.triangle(start);
+            triangle_box(a, b, c)
+        };
+        for i in start + 1..end {
+            let (a, b, c) = self.triangle(i);
+            boxx = Aabb::surrounding_box(boxx, triangle_box(a, b, c));
+        }
+        let index = self.nodes.len();
+        self.nodes.push(MeshNode {
+            boxx: boxx.clone(),
+            start,
+            end,
+            children: None,
+        });
+        if end - start <= 4 {
+            return index;
+        }
+        let extent = boxx.max - boxx.min;
+        let axis = if extent.x > extent.y && extent.x > extent.z {
+            0
+        } else if extent.y > extent.z {
+            1
+        } else {
+            2
+        };
+        let vertices = &self.vertices;
+        let key = |face: &[usize; 3]| {
+            let c = vertices[face[0]] + vertices[face[1]] + vertices[face[2]];
+            match axis {
+                0 => c.x,
+                1 => c.y,
+                _ => c.z,
+            }
+        };
+        self.faces[start..end].sort_by(|a, b| {
+            key(a)
+                .partial_cmp(&key(b))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let mid = (start + end) / 2;
+        let left = self.build(start, mid);
+        let right = self.build(mid, end);
+        self.nodes[index].children = Some((left, right));
+        index
+    }
+}
+impl<T: Material> Object for Mesh<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let mut ans = None;
+        let mut closest = t_max;
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if !node.boxx.hit(ray, t_min, closest) {
+                continue;
+            }
+            match node.children {
+                Some((left, right)) => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+                None => {
+                    for i in node.start..node.end {
+                        let (a, b, c) = self.triangle(i);
+                        if let Some(x) = triangle_intersect(a, b, c, ray, t_min, closest) {
+                            closest = x.0;
+                            ans = Some((i, x));
+                        }
+                    }
+                }
+            }
+        }
+        let (i, (t, u, v)) = ans?;
+        let (a, b, c) = self.triangle(i);
+        let [ia, ib, ic] = self.faces[i];
+        let geometric_normal = Vec3::cross(b - a, c - a).unit();
+        let (mut dpdu, mut dpdv) = (b - a, c - a);
+        let (texture_u, texture_v) = if self.uvs.is_empty() {
+            (u, v)
+        } else {
+            let (du1, dv1) = (
+                self.uvs[ib].0 - self.uvs[ia].0,
+                self.uvs[ib].1 - self.uvs[ia].1,
+            );
+            let (du2, dv2) = (
+                self.uvs[ic].0 - self.uvs[ia].0,
+                self.uvs[ic].1 - self.uvs[ia].1,
+            );
+            let det = du1 * dv2 - dv1 * du2;
+            if det.abs() > 1e-12 {
+                dpdu = ((b - a) * dv2 - (c - a) * dv1) / det;
+                dpdv = ((c - a) * du1 - (b - a) * du2) / det;
+            }
+            let w = 1.0 - u - v;
+            (
+                self.uvs[ia].0 * w + self.uvs[ib].0 * u + self.uvs[ic].0 * v,
+                self.uvs[ia].1 * w + self.uvs[ib].1 * u + self.uvs[ic].1 * v,
+            )
+        };
+        let rec = HitRecord::new(
+            ray,
+            t,
+            geometric_normal,
+            dpdu,
+            dpdv,
+            &self.material,
+            texture_u,
+            texture_v,
+        );
+        if self.normals.is_empty() {
+            return Some(rec);
+        }
+        let n = self.normals[ia] * (1.0 - u - v) + self.normals[ib] * u + self.normals[ic] * v;
+        if n.squared_length() > 0.0 {
+            Some(rec.with_shading_normal(n.unit()))
+        } else {
+            Some(rec)
+        }
+    }
+    fn bounding_box(&self, _t1: f64, _t2: f64) -> Option<Aabb> {
+        self.nodes.first().map(|x| x.boxx.clone())
+    }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        surface_pdf_value(self, origin, v, self.area)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        let (i, _) = self.area_table.sample(rng);
+        let (a, b, c) = self.triangle(i);
+        triangle_sample(a, b, c, rng) - origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn octahedron() -> (Vec<Vec3>, Vec<[usize; 3]>) {
+        let vertices = vec![
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        ];
+        let faces = vec![
+            [0, 2, 4],
+            [2, 1, 4],
+            [1, 3, 4],
+            [3, 0, 4],
+            [2, 0, 5],
+            [1, 2, 5],
+            [3, 1, 5],
+            [0, 3, 5],
+        ];
+        (vertices, faces)
+    }
+
+    #[test]
+    fn test_mesh_pdf() {
+        let (vertices, faces) = octahedron();
+        let mesh = Mesh::new(
+            vertices,
+            faces,
+            Lambertian {
+                albedo: SolidColor {
+                    color: Vec3::ones(),
+                },
+            },
+        );
+        let origin = Vec3::new(0.5, 1.5, -1.3);
+        let mut rng = SmallRng::seed_from_u64(0);
+        let n = 100000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let pdf = mesh.pdf_value(origin, mesh.random(origin, &mut rng));
+            if pdf > 0.0 {
+                sum += 1.0 / pdf;
+            }
+        }
+        let mut hits = 0;
+        for _ in 0..n {
+            let v = random_unit_vector(&mut rng);
+            if mesh
+                .hit(&Ray::new(origin, v, 0.0), 0.001, f64::MAX)
+                .is_some()
+            {
+                hits += 1;
+            }
+        }
+        let solid_angle = 4.0 * std::f64::consts::PI * hits as f64 / n as f64;
+        assert!((sum / n as f64 - solid_angle).abs() < 0.05 * solid_angle);
+    }
+
+    #[test]
+    fn test_smooth_mesh_pdf() {
+        let (vertices, faces) = octahedron();
+        let mesh = Mesh::with_attributes(
+            vertices.clone(),
+            vertices,
+            vec![],
+            faces,
+            Lambertian {
+                albedo: SolidColor {
+                    color: Vec3::ones(),
+                },
+            },
+        );
+        let origin = Vec3::new(0.5, 1.5, -1.3);
+        let mut rng = SmallRng::seed_from_u64(0);
+        let n = 200000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            sum += mesh.pdf_value(origin, random_unit_vector(&mut rng));
+        }
+        let integral = 4.0 * std::f64::consts::PI * sum / n as f64;
+        assert!((integral - 1.0).abs() < 0.05);
+    }
+}
@@ -1,9 +1,15 @@
 use rand::{rngs::SmallRng, Rng};
+use std::sync::Arc;
 
 pub use crate::bvh::*;
+pub use crate::csg::*;
+pub use crate::lights::*;
 pub use crate::materials::*;
+pub use crate::mesh::*;
 pub use crate::pdf::*;
 pub use crate::ray::*;
+pub use crate::sdf::*;
+pub use crate::shapes::*;
 pub use crate::transforms::*;
 pub use crate::vec3::*;
 
@@ -12,10 +18,51 @@ pub struct HitRecord<'a> {
     pub t: f64,
     pub p: Vec3,
     pub normal: Vec3,
+    pub geometric_normal: Vec3,
+    pub tangent: Vec3,
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+    pub front_face: bool,
+    pub uv_footprint: (f64, f64),
     pub mat_ptr: &'a dyn Material,
     pub u: f64,
     pub v: f64,
 }
+impl<'a> HitRecord<'a> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        ray: &Ray,
+        t: f64,
+        normal: Vec3,
+        dpdu: Vec3,
+        dpdv: Vec3,
+        mat_ptr: &'a dyn Material,
+        u: f64,
+        v: f64,
+    ) -> Self {
+        Self {
+            t,
+            p: ray.at(t),
+            normal,
+            geometric_normal: normal,
+            tangent: surface_tangent(normal, dpdu),
+            dpdu,
+            dpdv,
+            front_face: ray.dir * normal < 0.0,
+            uv_footprint: uv_footprint(ray, t, normal, dpdu, dpdv),
+            mat_ptr,
+            u,
+            v,
+        }
+    }
+    pub fn with_shading_normal(self, normal: Vec3) -> Self {
+        Self {
+            normal,
+            tangent: surface_tangent(normal, self.dpdu),
+            ..self
+        }
+    }
+}
 pub trait Object: Sync + Send {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
     fn bounding_box(&self, t1: f64, t2: f64) -> Option<Aabb>;
@@ -23,9 +70,154 @@ pub trait Object: Sync + Send {
         0.0
     }
     fn random(&self, _origin: Vec3, _rng: &mut SmallRng) -> Vec3 {
+        debug_assert!(false, "Object:random is not implemented for this object");
         Vec3::new(1.0, 0.0, 0.0)
     }
+    /// Every surface crossing along the ray, sorted by t. CSG and participating media pair
+    /// these up into inside intervals, so opacity masks are not applied here; only `hit`
+    /// skips masked-out surfaces.
+    fn hit_all(&self, ray: &Ray) -> Vec<HitRecord> {
+        let mut ans = vec![];
+        let mut t = f64::MIN;
+        while ans.len() < 64 {
+            match self.hit(ray, t, f64::MAX) {
+                Some(rec) => {
+                    t = rec.t + 0.0001;
+                    ans.push(rec);
+                }
+                None => break,
+            }
+        }
+        ans
+    }
+}
+impl<T: Object + ?Sized> Object for Arc<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        (**self).hit(ray, t_min, t_max)
+    }
+    fn bounding_box(&self, t1: f64, t2: f64) -> Option<Aabb> {
+        (**self).bounding_box(t1, t2)
+    }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        (**self).pdf_value(origin, v)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        (**self).random(origin, rng)
+    }
+    fn hit_all(&self, ray: &Ray) -> Vec<HitRecord> {
+        (**self).hit_all(ray)
+    }
+}
+
+pub fn surface_tangent(normal: Vec3, dpdu: Vec3) -> Vec3 {
+    let tangent = dpdu - normal * (normal * dpdu);
+    if tangent.squared_length() > 1e-12 {
+        tangent.unit()
+    } else {
+        ONB::build_from_w(normal).u
+    }
+}
+
+pub fn uv_footprint(ray: &Ray, t: f64, normal: Vec3, dpdu: Vec3, dpdv: Vec3) -> (f64, f64) {
+    let differential = match &ray.differential {
+        Some(x) => x,
+        None => return (0.0, 0.0),
+    };
+    let p = ray.at(t);
+    let a11 = dpdu * dpdu;
+    let a12 = dpdu * dpdv;
+    let a22 = dpdv * dpdv;
+    let det = a11 * a22 - a12 * a12;
+    if det.abs() < 1e-20 {
+        return (0.0, 0.0);
+    }
+    let mut footprint = (0.0, 0.0);
+    for &(ori, dir) in [
+        (differential.rx_ori, differential.rx_dir),
+        (differential.ry_ori, differential.ry_dir),
+    ]
+    .iter()
+    {
+        let denom = normal * dir;
+        if denom.abs() < 1e-12 {
+            return (0.0, 0.0);
+        }
+        let dp = ori + dir * (normal * (p - ori) / denom) - p;
+        let (b1, b2) = (dpdu * dp, dpdv * dp);
+        let du = (a22 * b1 - a12 * b2) / det;
+        let dv = (a11 * b2 - a12 * b1) / det;
+        footprint.0 = f64::max(footprint.0, du.abs());
+        footprint.1 = f64::max(footprint.1, dv.abs());
+    }
+    footprint
+}
+
+pub fn surface_pdf_value(object: &dyn Object, origin: Vec3, v: Vec3, area: f64) -> f64 {
+    let ray = Ray::new(origin, v, 0.0);
+    let mut ans = 0.0;
+    let mut t_min = 0.001;
+    while let Some(rec) = object.hit(&ray, t_min, f64::MAX) {
+        let distance_squared = rec.t * rec.t * v.squared_length();
+        let cosine = (v * rec.geometric_normal).abs() / v.length();
+        ans += distance_squared / (cosine * area);
+        t_min = rec.t + 0.0001;
+    }
+    ans
+}
+
+pub fn volume_pdf_value(object: &dyn Object, origin: Vec3, v: Vec3) -> f64 {
+    let boxx = match object.bounding_box(0.0, 1.0) {
+        Some(boxx) if boxx.volume() > 0.0 => boxx,
+        _ => return 0.0,
+    };
+    let ray = Ray::new(origin, v.unit(), 0.0);
+    match boxx.interval(&ray, 0.0, f64::MAX) {
+        Some((t0, t1)) => (t1.powi(3) - t0.powi(3)) / (3.0 * boxx.volume()),
+        None => 0.0,
+    }
+}
+
+pub fn volume_random(object: &dyn Object, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+    match object.bounding_box(0.0, 1.0) {
+        Some(boxx) => {
+            Vec3::new(
+                rng.gen_range(boxx.min.x, boxx.max.x),
+                rng.gen_range(boxx.min.y, boxx.max.y),
+                rng.gen_range(boxx.min.z, boxx.max.z),
+            ) - origin
+        }
+        None => random_unit_vector(rng),
+    }
 }
+
+fn alpha_threshold(ray: &Ray, t: f64) -> f64 {
+    let mut h: u64 = 0x9e37_79b9_7f4a_7c15;
+    for x in [
+        ray.ori.x, ray.ori.y, ray.ori.z, ray.dir.x, ray.dir.y, ray.dir.z, t,
+    ]
+    .iter()
+    {
+        h = (h ^ x.to_bits()).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        h ^= h >> 31;
+    }
+    (h >> 11) as f64 / (1u64 << 53) as f64
+}
+pub fn hit_opaque<'a, F: Fn(f64) -> Option<HitRecord<'a>>>(
+    hit: F,
+    ray: &Ray,
+    t_min: f64,
+) -> Option<HitRecord<'a>> {
+    let mut t_min = t_min;
+    loop {
+        let rec = hit(t_min)?;
+        let opacity = rec.mat_ptr.opacity(&rec);
+        if opacity >= 1.0 || (opacity > 0.0 && alpha_threshold(ray, rec.t) < opacity) {
+            return Some(rec);
+        }
+        t_min = rec.t + 0.0001;
+    }
+}
+
 pub struct ObjectList {
     pub objects: Vec<Box<dyn Object>>,
 }
@@ -36,15 +228,21 @@ impl ObjectList {
 }
 impl Object for ObjectList {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let mut ans: Option<HitRecord> = None;
-        let mut closest = t_max;
-        for x in self.objects.iter() {
-            if let Some(tmp) = x.hit(ray, t_min, closest) {
-                ans = Some(tmp.clone());
-                closest = tmp.t;
-            }
-        }
-        ans
+        hit_opaque(
+            |t_min| {
+                let mut ans: Option<HitRecord> = None;
+                let mut closest = t_max;
+                for x in self.objects.iter() {
+                    if let Some(tmp) = x.hit(ray, t_min, closest) {
+                        closest = tmp.t;
+                        ans = Some(tmp);
+                    }
+                }
+                ans
+            },
+            ray,
+            t_min,
+        )
     }
     fn bounding_box(&self, t1: f64, t2: f64) -> Option<Aabb> {
         if self.objects.is_empty() {
@@ -76,6 +274,11 @@ impl Object for ObjectList {
     fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
         self.objects[rng.gen_range(0, self.objects.len())].random(origin, rng)
     }
+    fn hit_all(&self, ray: &Ray) -> Vec<HitRecord> {
+        let mut ans: Vec<HitRecord> = self.objects.iter().flat_map(|x| x.hit_all(ray)).collect();
+        ans.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        ans
+    }
 }
 
 pub struct Sphere<T: Material> {
@@ -91,28 +294,27 @@ impl<T: Material> Object for Sphere<T> {
         let c = oc * oc - self.radius * self.radius;
         let discriminant = half_b * half_b - a * c;
         if discriminant > 0.0 {
-            let ans = (-half_b - discriminant.sqrt()) / a;
-            let (texture_u, texture_v) = get_sphere_uv((ray.at(ans) - self.center) / self.radius);
-            if ans > t_min && ans < t_max {
-                return Some(HitRecord {
-                    t: ans,
-                    p: ray.at(ans),
-                    normal: (ray.at(ans) - self.center) / self.radius,
-                    mat_ptr: &self.material,
-                    u: texture_u,
-                    v: texture_v,
-                });
-            }
-            let ans = (-half_b + discriminant.sqrt()) / a;
-            if ans > t_min && ans < t_max {
-                return Some(HitRecord {
-                    t: ans,
-                    p: ray.at(ans),
-                    normal: (ray.at(ans) - self.center) / self.radius,
-                    mat_ptr: &self.material,
-                    u: texture_u,
-                    v: texture_v,
-                });
+            for &ans in [
+                (-half_b - discriminant.sqrt()) / a,
+                (-half_b + discriminant.sqrt()) / a,
+            ]
+            .iter()
+            {
+                if ans > t_min && ans < t_max {
+                    let normal = (ray.at(ans) - self.center) / self.radius;
+                    let (texture_u, texture_v) = get_sphere_uv(normal);
+                    let (dpdu, dpdv) = sphere_derivatives(normal, self.radius);
+                    return Some(HitRecord::new(
+                        ray,
+                        ans,
+                        normal,
+                        dpdu,
+                        dpdv,
+                        &self.material,
+                        texture_u,
+                        texture_v,
+                    ));
+                }
             }
         }
         None
@@ -164,29 +366,27 @@ impl<T: Material> Object for MovingSphere<T> {
         let c = oc * oc - self.radius * self.radius;
         let discriminant = half_b * half_b - a * c;
         if discriminant > 0.0 {
-            let ans = (-half_b - discriminant.sqrt()) / a;
-            let (texture_u, texture_v) =
-                get_sphere_uv((ray.at(ans) - self.get_center(ray.time)) / self.radius);
-            if ans > t_min && ans < t_max {
-                return Some(HitRecord {
-                    t: ans,
-                    p: ray.at(ans),
-                    normal: (ray.at(ans) - self.get_center(ray.time)) / self.radius,
-                    mat_ptr: &self.material,
-                    u: texture_u,
-                    v: texture_v,
-                });
-            }
-            let ans = (-half_b + discriminant.sqrt()) / a;
-            if ans > t_min && ans < t_max {
-                return Some(HitRecord {
-                    t: ans,
-                    p: ray.at(ans),
-                    normal: (ray.at(ans) - self.get_center(ray.time)) / self.radius,
-                    mat_ptr: &self.material,
-                    u: texture_u,
-                    v: texture_v,
-                });
+            for &ans in [
+                (-half_b - discriminant.sqrt()) / a,
+                (-half_b + discriminant.sqrt()) / a,
+            ]
+            .iter()
+            {
+                if ans > t_min && ans < t_max {
+                    let normal = (ray.at(ans) - self.get_center(ray.time)) / self.radius;
+                    let (texture_u, texture_v) = get_sphere_uv(normal);
+                    let (dpdu, dpdv) = sphere_derivatives(normal, self.radius);
+                    return Some(HitRecord::new(
+                        ray,
+                        ans,
+                        normal,
+                        dpdu,
+                        dpdv,
+                        &self.material,
+                        texture_u,
+                        texture_v,
+                    ));
+                }
             }
         }
         None
@@ -203,9 +403,32 @@ impl<T: Material> Object for MovingSphere<T> {
             },
         ))
     }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        let center = (self.center1 + self.center2) * 0.5;
+        let radius = self.radius + (self.center2 - self.center1).length() * 0.5;
+        let distance_squared = (center - origin).squared_length();
+        if distance_squared <= radius * radius {
+            return 1.0 / (4.0 * std::f64::consts::PI);
+        }
+        let cos_theta_max = (1.0 - radius * radius / distance_squared).sqrt();
+        if (center - origin) * v < cos_theta_max * (center - origin).length() * v.length() {
+            return 0.0;
+        }
+        1.0 / (2.0 * std::f64::consts::PI * (1.0 - cos_theta_max))
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        let center = (self.center1 + self.center2) * 0.5;
+        let radius = self.radius + (self.center2 - self.center1).length() * 0.5;
+        let direction = center - origin;
+        if direction.squared_length() <= radius * radius {
+            return random_unit_vector(rng);
+        }
+        let uvw = ONB::build_from_w(direction);
+        uvw.local(random_to_sphere(radius, direction.squared_length(), rng))
+    }
 }
 
-fn get_sphere_uv(p: Vec3) -> (f64, f64) {
+pub fn get_sphere_uv(p: Vec3) -> (f64, f64) {
     let phi = p.z.atan2(p.x);
     let theta = p.y.asin();
     (
@@ -213,6 +436,22 @@ fn get_sphere_uv(p: Vec3) -> (f64, f64) {
         (theta + std::f64::consts::PI / 2.0) / std::f64::consts::PI,
     )
 }
+pub fn sphere_derivatives(normal: Vec3, radius: f64) -> (Vec3, Vec3) {
+    let pi = std::f64::consts::PI;
+    let cos_theta = (normal.x * normal.x + normal.z * normal.z).sqrt();
+    if cos_theta < 1e-12 {
+        let uvw = ONB::build_from_w(normal);
+        return (uvw.u * (2.0 * pi * radius), uvw.v * (pi * radius));
+    }
+    (
+        Vec3::new(normal.z, 0.0, -normal.x) * (2.0 * pi * radius),
+        Vec3::new(
+            -normal.y * normal.x / cos_theta,
+            cos_theta,
+            -normal.y * normal.z / cos_theta,
+        ) * (pi * radius),
+    )
+}
 
 pub struct RectXY<T: Material> {
     pub x1: f64,
@@ -230,14 +469,16 @@ impl<T: Material> Object for RectXY<T> {
             let x = ray.ori.x + t * ray.dir.x;
             let y = ray.ori.y + t * ray.dir.y;
             if x >= self.x1 && x <= self.x2 && y >= self.y1 && y <= self.y2 {
-                return Some(HitRecord {
+                return Some(HitRecord::new(
+                    ray,
                     t,
-                    p: ray.at(t),
-                    normal: Vec3::new(0.0, 0.0, 1.0) * self.face,
-                    mat_ptr: &self.material,
-                    u: (x - self.x1) / (self.x2 - self.x1),
-                    v: (y - self.y1) / (self.y2 - self.y1),
-                });
+                    Vec3::new(0.0, 0.0, 1.0) * self.face,
+                    Vec3::new(self.x2 - self.x1, 0.0, 0.0),
+                    Vec3::new(0.0, self.y2 - self.y1, 0.0),
+                    &self.material,
+                    (x - self.x1) / (self.x2 - self.x1),
+                    (y - self.y1) / (self.y2 - self.y1),
+                ));
             }
         }
         None
@@ -284,14 +525,16 @@ impl<T: Material> Object for RectXZ<T> {
             let x = ray.ori.x + t * ray.dir.x;
             let z = ray.ori.z + t * ray.dir.z;
             if x >= self.x1 && x <= self.x2 && z >= self.z1 && z <= self.z2 {
-                return Some(HitRecord {
+                return Some(HitRecord::new(
+                    ray,
                     t,
-                    p: ray.at(t),
-                    normal: Vec3::new(0.0, 1.0, 0.0) * self.face,
-                    mat_ptr: &self.material,
-                    u: (x - self.x1) / (self.x2 - self.x1),
-                    v: (z - self.z1) / (self.z2 - self.z1),
-                });
+                    Vec3::new(0.0, 1.0, 0.0) * self.face,
+                    Vec3::new(self.x2 - self.x1, 0.0, 0.0),
+                    Vec3::new(0.0, 0.0, self.z2 - self.z1),
+                    &self.material,
+                    (x - self.x1) / (self.x2 - self.x1),
+                    (z - self.z1) / (self.z2 - self.z1),
+                ));
             }
         }
         None
@@ -338,14 +581,16 @@ impl<T: Material> Object for RectYZ<T> {
             let y = ray.ori.y + t * ray.dir.y;
             let z = ray.ori.z + t * ray.dir.z;
             if y >= self.y1 && y <= self.y2 && z >= self.z1 && z <= self.z2 {
-                return Some(HitRecord {
+                return Some(HitRecord::new(
+                    ray,
                     t,
-                    p: ray.at(t),
-                    normal: Vec3::new(1.0, 0.0, 0.0) * self.face,
-                    mat_ptr: &self.material,
-                    u: (y - self.y1) / (self.y2 - self.y1),
-                    v: (z - self.z1) / (self.z2 - self.z1),
-                });
+                    Vec3::new(1.0, 0.0, 0.0) * self.face,
+                    Vec3::new(0.0, self.y2 - self.y1, 0.0),
+                    Vec3::new(0.0, 0.0, self.z2 - self.z1),
+                    &self.material,
+                    (y - self.y1) / (self.y2 - self.y1),
+                    (z - self.z1) / (self.z2 - self.z1),
+                ));
             }
         }
         None
@@ -511,6 +756,19 @@ impl<T: Material + Clone> Object for Cuboid<T> {
     }
 }
 
+fn boundary_intervals(boundary: &dyn Object, ray: &Ray, t_min: f64, t_max: f64) -> Vec<(f64, f64)> {
+    let hits = boundary.hit_all(ray);
+    let mut ans = vec![];
+    for pair in hits.chunks_exact(2) {
+        let t1 = pair[0].t.max(t_min);
+        let t2 = pair[1].t.min(t_max);
+        if t1 < t2 {
+            ans.push((t1, t2));
+        }
+    }
+    ans
+}
+
 pub struct ConstantMedium<T1: Object, T2: Material> {
     pub boundary: T1,
     pub phase_function: T2,
@@ -525,30 +783,172 @@ impl<T1: Object, T2: Texture> ConstantMedium<T1, Isotropic<T2>> {
         }
     }
 }
+impl<T1: Object, T2: Texture> ConstantMedium<T1, PhaseFunction<T2>> {
+    pub fn with_phase(boundary: T1, texture: T2, density: f64, phase: Phase) -> Self {
+        Self {
+            boundary,
+            phase_function: PhaseFunction {
+                albedo: texture,
+                phase,
+            },
+            density,
+        }
+    }
+}
 impl<T1: Object, T2: Material> Object for ConstantMedium<T1, T2> {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        if let Some(mut rec1) = self.boundary.hit(ray, f64::MIN, f64::MAX) {
-            if let Some(mut rec2) = self.boundary.hit(ray, rec1.t + 0.0001, f64::MAX) {
-                if rec1.t < t_min {
-                    rec1.t = t_min;
-                }
-                if rec2.t > t_max {
-                    rec2.t = t_max;
+        let mut hit_distance = -(1.0 / self.density) * rand::random::<f64>().ln();
+        for (t1, t2) in boundary_intervals(&self.boundary, ray, t_min, t_max) {
+            let distance_inside_boundary = (t2 - t1) * ray.dir.length();
+            if hit_distance <= distance_inside_boundary {
+                let t = t1 + hit_distance / ray.dir.length();
+                return Some(HitRecord::new(
+                    ray,
+                    t,
+                    -ray.dir.unit(),
+                    Vec3::zero(),
+                    Vec3::zero(),
+                    &self.phase_function,
+                    0.0,
+                    0.0,
+                ));
+            }
+            hit_distance -= distance_inside_boundary;
+        }
+        None
+    }
+    fn bounding_box(&self, t1: f64, t2: f64) -> Option<Aabb> {
+        self.boundary.bounding_box(t1, t2)
+    }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        self.boundary.pdf_value(origin, v)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        self.boundary.random(origin, rng)
+    }
+}
+
+pub struct Subsurface<T1: Object, T2: Texture> {
+    pub boundary: T1,
+    pub interface: SubsurfaceBoundary,
+    pub random_walk: RandomWalk<SingleScatterAlbedo<T2>>,
+    pub mean_free_path: f64,
+}
+impl<T1: Object, T2: Texture> Subsurface<T1, T2> {
+    pub fn new(boundary: T1, albedo: T2, mean_free_path: f64, ref_idx: f64, g: f64) -> Self {
+        Self {
+            boundary,
+            interface: SubsurfaceBoundary { ref_idx },
+            random_walk: RandomWalk {
+                albedo: SingleScatterAlbedo { albedo },
+                phase: Phase::HenyeyGreenstein { g },
+            },
+            mean_free_path,
+        }
+    }
+}
+impl<T1: Object, T2: Texture> Object for Subsurface<T1, T2> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let rec = self.boundary.hit(ray, t_min, f64::MAX)?;
+        if ray.dir * rec.normal > 0.0 {
+            let hit_distance = -self.mean_free_path * rand::random::<f64>().ln();
+            let t = t_min + hit_distance / ray.dir.length();
+            if t < rec.t && t < t_max {
+                return Some(HitRecord::new(
+                    ray,
+                    t,
+                    -ray.dir.unit(),
+                    Vec3::zero(),
+                    Vec3::zero(),
+                    &self.random_walk,
+                    rec.u,
+                    rec.v,
+                ));
+            }
+        }
+        if rec.t < t_max {
+            return Some(HitRecord {
+                mat_ptr: &self.interface,
+                ..rec
+            });
+        }
+        None
+    }
+    fn bounding_box(&self, t1: f64, t2: f64) -> Option<Aabb> {
+        self.boundary.bounding_box(t1, t2)
+    }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        self.boundary.pdf_value(origin, v)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        self.boundary.random(origin, rng)
+    }
+}
+
+pub struct HeterogeneousMedium<T1: Object, T2: Texture, T3: Material> {
+    pub boundary: T1,
+    pub density: T2,
+    pub phase_function: T3,
+    pub max_density: f64,
+    pub sigma_a: f64,
+    pub sigma_s: f64,
+}
+impl<T1: Object, T2: Texture, T3: Texture> HeterogeneousMedium<T1, T2, Isotropic<T3>> {
+    pub fn new(
+        boundary: T1,
+        density: T2,
+        max_density: f64,
+        texture: T3,
+        sigma_a: f64,
+        sigma_s: f64,
+    ) -> Self {
+        Self {
+            boundary,
+            density,
+            phase_function: Isotropic { albedo: texture },
+            max_density,
+            sigma_a,
+            sigma_s,
+        }
+    }
+}
+impl<T1: Object, T2: Texture, T3: Material> HeterogeneousMedium<T1, T2, T3> {
+    pub fn density_at(&self, p: Vec3) -> f64 {
+        let d = self.density.value(0.0, 0.0, p);
+        num::clamp((d.x + d.y + d.z) / 3.0, 0.0, self.max_density)
+    }
+}
+impl<T1: Object, T2: Texture, T3: Material> Object for HeterogeneousMedium<T1, T2, T3> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let sigma_t = self.sigma_a + self.sigma_s;
+        let majorant = self.max_density * sigma_t;
+        if majorant <= 0.0 {
+            return None;
+        }
+        for (t1, t2) in boundary_intervals(&self.boundary, ray, t_min, t_max) {
+            let mut t = t1;
+            loop {
+                t -= (1.0 - rand::random::<f64>()).ln() / (majorant * ray.dir.length());
+                if t >= t2 {
+                    break;
                 }
-                if rec1.t < rec2.t {
-                    let distance_inside_boundary = (rec2.t - rec1.t) * ray.dir.length();
-                    let hit_distance = -(1.0 / self.density) * rand::random::<f64>().ln();
-                    if hit_distance <= distance_inside_boundary {
-                        let t = rec1.t + hit_distance / ray.dir.length();
-                        return Some(HitRecord {
-                            t,
-                            p: ray.at(t),
-                            normal: Vec3::new(1.0, 0.0, 0.0),
-                            mat_ptr: &self.phase_function,
-                            u: 0.0,
-                            v: 0.0,
-                        });
-                    }
+                let p = ray.at(t);
+                if rand::random::<f64>() * self.max_density < self.density_at(p) {
+                    let mat_ptr: &dyn Material = if rand::random::<f64>() * sigma_t < self.sigma_a {
+                        &Absorber
+                    } else {
+                        &self.phase_function
+                    };
+                    return Some(HitRecord::new(
+                        ray,
+                        t,
+                        -ray.dir.unit(),
+                        Vec3::zero(),
+                        Vec3::zero(),
+                        mat_ptr,
+                        0.0,
+                        0.0,
+                    ));
                 }
             }
         }
@@ -557,4 +957,10 @@ impl<T1: Object, T2: Material> Object for ConstantMedium<T1, T2> {
     fn bounding_box(&self, t1: f64, t2: f64) -> Option<Aabb> {
         self.boundary.bounding_box(t1, t2)
     }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        self.boundary.pdf_value(origin, v)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        self.boundary.random(origin, rng)
+    }
 }
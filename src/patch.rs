@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+pub use crate::mesh::*;
+
+fn bernstein(t: f64) -> [f64; 4] {
+    let s = 1.0 - t;
+    [s * s * s, 3.0 * t * s * s, 3.0 * t * t * s, t * t * t]
+}
+fn bernstein_derivative(t: f64) -> [f64; 4] {
+    let s = 1.0 - t;
+    [
+        -3.0 * s * s,
+        3.0 * s * s - 6.0 * t * s,
+        6.0 * t * s - 3.0 * t * t,
+        3.0 * t * t,
+    ]
+}
+fn bezier_eval(control: &[Vec3; 16], u: f64, v: f64) -> (Vec3, Vec3, Vec3) {
+    let (bu, bv) = (bernstein(u), bernstein(v));
+    let (du, dv) = (bernstein_derivative(u), bernstein_derivative(v));
+    let mut p = Vec3::zero();
+    let mut dpdu = Vec3::zero();
+    let mut dpdv = Vec3::zero();
+    for i in 0..4 {
+        for j in 0..4 {
+            let c = control[i * 4 + j];
+            p += c * (bu[i] * bv[j]);
+            dpdu += c * (du[i] * bv[j]);
+            dpdv += c * (bu[i] * dv[j]);
+        }
+    }
+    (p, dpdu, dpdv)
+}
+fn bezier_normal(control: &[Vec3; 16], u: f64, v: f64) -> Vec3 {
+    let (_, dpdu, dpdv) = bezier_eval(control, u, v);
+    let n = Vec3::cross(dpdu, dpdv);
+    if n.squared_length() > 1e-20 {
+        return n.unit();
+    }
+    let delta = 1e-4;
+    let (_, dpdu, dpdv) = bezier_eval(
+        control,
+        u + if u < 0.5 { delta } else { -delta },
+        v + if v < 0.5 { delta } else { -delta },
+    );
+    let n = Vec3::cross(dpdu, dpdv);
+    if n.squared_length() > 0.0 {
+        n.unit()
+    } else {
+        n
+    }
+}
+
+type Cage = (Vec<Vec3>, Vec<(f64, f64)>, Vec<Vec<usize>>);
+
+fn catmull_clark_step(vertices: &[Vec3], uvs: &[(f64, f64)], faces: &[Vec<usize>]) -> Cage {
+    let has_uv = !uvs.is_empty();
+    let n = vertices.len();
+    let mut edges: HashMap<(usize, usize), (usize, Vec<usize>)> = HashMap::new();
+    for (f, face) in faces.iter().enumerate() {
+        for k in 0..face.len() {
+            let (a, b) = (face[k], face[(k + 1) % face.len()]);
+            let count = edges.len();
+            edges
+                .entry((a.min(b), a.max(b)))
+                .or_insert((count, vec![]))
+                .1
+                .push(f);
+        }
+    }
+    let face_base = n;
+    let edge_base = n + faces.len();
+    let mut new_vertices = vec![Vec3::zero(); edge_base + edges.len()];
+    let mut new_uvs = if has_uv {
+        vec![(0.0, 0.0); edge_base + edges.len()]
+    } else {
+        vec![]
+    };
+    for (f, face) in faces.iter().enumerate() {
+        let weight = 1.0 / face.len() as f64;
+        for &i in face.iter() {
+            new_vertices[face_base + f] += vertices[i] * weight;
+            if has_uv {
+                new_uvs[face_base + f].0 += uvs[i].0 * weight;
+                new_uvs[face_base + f].1 += uvs[i].1 * weight;
+            }
+        }
+    }
+    let mut face_sum = vec![Vec3::zero(); n];
+    let mut edge_sum = vec![Vec3::zero(); n];
+    let mut boundary_sum = vec![Vec3::zero(); n];
+    let mut face_count = vec![0usize; n];
+    let mut edge_count = vec![0usize; n];
+    let mut boundary_count = vec![0usize; n];
+    for (f, face) in faces.iter().enumerate() {
+        for &i in face.iter() {
+            face_sum[i] += new_vertices[face_base + f];
+            face_count[i] += 1;
+        }
+    }
+    for (&(a, b), (index, adjacent)) in edges.iter() {
+        let midpoint = (vertices[a] + vertices[b]) * 0.5;
+        new_vertices[edge_base + index] = if adjacent.len() == 2 {
+            (vertices[a]
+                + vertices[b]
+                + new_vertices[face_base + adjacent[0]]
+                + new_vertices[face_base + adjacent[1]])
+                * 0.25
+        } else {
+            midpoint
+        };
+        if has_uv {
+            new_uvs[edge_base + index] = ((uvs[a].0 + uvs[b].0) * 0.5, (uvs[a].1 + uvs[b].1) * 0.5);
+        }
+        for &(i, j) in [(a, b), (b, a)].iter() {
+            edge_sum[i] += midpoint;
+            edge_count[i] += 1;
+            if adjacent.len() != 2 {
+                boundary_sum[i] += vertices[j];
+                boundary_count[i] += 1;
+            }
+        }
+    }
+    for i in 0..n {
+        new_vertices[i] = if boundary_count[i] > 0 {
+            if boundary_count[i] == 2 {
+                vertices[i] * 0.75 + boundary_sum[i] * 0.125
+            } else {
+                vertices[i]
+            }
+        } else if face_count[i] > 0 {
+            let valence = edge_count[i] as f64;
+            let f = face_sum[i] / face_count[i] as f64;
+            let r = edge_sum[i] / valence;
+            (f + r * 2.0 + vertices[i] * (valence - 3.0)) / valence
+        } else {
+            vertices[i]
+        };
+        if has_uv {
+            new_uvs[i] = uvs[i];
+        }
+    }
+    let edge_point = |a: usize, b: usize| edge_base + edges[&(a.min(b), a.max(b))].0;
+    let mut new_faces = vec![];
+    for (f, face) in faces.iter().enumerate() {
+        let len = face.len();
+        for k in 0..len {
+            let prev = face[(k + len - 1) % len];
+            let next = face[(k + 1) % len];
+            new_faces.push(vec![
+                face[k],
+                edge_point(face[k], next),
+                face_base + f,
+                edge_point(prev, face[k]),
+            ]);
+        }
+    }
+    (new_vertices, new_uvs, new_faces)
+}
+
+impl<T: Material> Mesh<T> {
+    pub fn bezier_patches(patches: &[[Vec3; 16]], level: usize, material: T) -> Self {
+        let segments = 1 << level;
+        let mut vertices = vec![];
+        let mut normals = vec![];
+        let mut uvs = vec![];
+        let mut faces = vec![];
+        for control in patches.iter() {
+            let base = vertices.len();
+            for i in 0..=segments {
+                for j in 0..=segments {
+                    let u = i as f64 / segments as f64;
+                    let v = j as f64 / segments as f64;
+                    vertices.push(bezier_eval(control, u, v).0);
+                    normals.push(bezier_normal(control, u, v));
+                    uvs.push((u, v));
+                }
+            }
+            let index = |i: usize, j: usize| base + i * (segments + 1) + j;
+            for i in 0..segments {
+                for j in 0..segments {
+                    faces.push([index(i, j), index(i + 1, j), index(i + 1, j + 1)]);
+                    faces.push([index(i, j), index(i + 1, j + 1), index(i, j + 1)]);
+                }
+            }
+        }
+        Self::with_attributes(vertices, normals, uvs, faces, material)
+    }
+    pub fn load_bpt(filename: &str, level: usize, material: T) -> Self {
+        let content = std::fs::read_to_string(filename).expect("Mesh:failed to open file");
+        let mut numbers = content
+            .split_whitespace()
+            .map(|x| x.parse::<f64>().expect("Mesh:invalid patch file"));
+        let mut next = || numbers.next().expect("Mesh:truncated patch file");
+        let count = next() as usize;
+        let mut patches = vec![];
+        for _ in 0..count {
+            let (degree_u, degree_v) = (next() as usize, next() as usize);
+            if degree_u != 3 || degree_v != 3 {
+                panic!("Mesh:only bicubic patches are supported");
+            }
+            let mut control = [Vec3::zero(); 16];
+            for c in control.iter_mut() {
+                *c = Vec3::new(next(), next(), next());
+            }
+            patches.push(control);
+        }
+        Self::bezier_patches(&patches, level, material)
+    }
+    pub fn catmull_clark(
+        vertices: Vec<Vec3>,
+        uvs: Vec<(f64, f64)>,
+        faces: Vec<Vec<usize>>,
+        level: usize,
+        material: T,
+    ) -> Self {
+        let (mut vertices, mut uvs, mut faces) = (vertices, uvs, faces);
+        for _ in 0..level {
+            let (v, uv, f) = catmull_clark_step(&vertices, &uvs, &faces);
+            vertices = v;
+            uvs = uv;
+            faces = f;
+        }
+        let mut triangles = vec![];
+        for index in faces {
+            for k in 1..index.len().saturating_sub(1) {
+                triangles.push([index[0], index[k], index[k + 1]]);
+            }
+        }
+        Self::with_attributes(vertices, vec![], uvs, triangles, material).smooth_normals()
+    }
+    pub fn load_subdivision_obj(filename: &str, level: usize, material: T) -> Self {
+        let (vertices, faces) = read_obj(filename);
+        Self::catmull_clark(vertices, vec![], faces, level, material)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patch_tessellation() {
+        let mut control = [Vec3::zero(); 16];
+        for i in 0..4 {
+            for j in 0..4 {
+                control[i * 4 + j] = Vec3::new(i as f64 / 3.0, j as f64 / 3.0, 0.0);
+            }
+        }
+        let patch = Mesh::bezier_patches(
+            &[control],
+            3,
+            Lambertian {
+                albedo: SolidColor {
+                    color: Vec3::ones(),
+                },
+            },
+        );
+        assert_eq!(patch.faces.len(), 2 * 8 * 8);
+        let ray = Ray::new(Vec3::new(0.3, 0.6, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = patch.hit(&ray, 0.001, f64::MAX).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+        assert!((rec.u - 0.3).abs() < 1e-9 && (rec.v - 0.6).abs() < 1e-9);
+
+        let vertices = vec![
+            Vec3::new(-1.0, -1.0, -1.0),
+            Vec3::new(1.0, -1.0, -1.0),
+            Vec3::new(1.0, 1.0, -1.0),
+            Vec3::new(-1.0, 1.0, -1.0),
+            Vec3::new(-1.0, -1.0, 1.0),
+            Vec3::new(1.0, -1.0, 1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+            Vec3::new(-1.0, 1.0, 1.0),
+        ];
+        let faces = vec![
+            vec![0, 3, 2, 1],
+            vec![4, 5, 6, 7],
+            vec![0, 1, 5, 4],
+            vec![2, 3, 7, 6],
+            vec![0, 4, 7, 3],
+            vec![1, 2, 6, 5],
+        ];
+        let cube = Mesh::catmull_clark(
+            vertices,
+            vec![],
+            faces,
+            3,
+            Lambertian {
+                albedo: SolidColor {
+                    color: Vec3::ones(),
+                },
+            },
+        );
+        assert_eq!(cube.faces.len(), 6 * 64 * 2);
+        for (p, n) in cube.vertices.iter().zip(cube.normals.iter()) {
+            assert!(p.length() > 0.55 && p.length() < 1.0);
+            assert!(*p * *n > 0.0);
+        }
+    }
+}
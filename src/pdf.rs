@@ -2,6 +2,7 @@ use rand::{rngs::SmallRng, Rng};
 
 pub use crate::objects::*;
 
+#[derive(Clone)]
 pub struct ONB {
     pub u: Vec3,
     pub v: Vec3,
@@ -53,6 +54,81 @@ impl PDF for CosinePDF {
     }
 }
 
+#[derive(Clone, Copy)]
+pub enum Phase {
+    Isotropic,
+    HenyeyGreenstein { g: f64 },
+    DoubleHenyeyGreenstein { g1: f64, g2: f64, weight: f64 },
+    Rayleigh,
+}
+fn henyey_greenstein(g: f64, cos_theta: f64) -> f64 {
+    let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+    (1.0 - g * g) / (4.0 * std::f64::consts::PI * denom * denom.sqrt())
+}
+fn sample_henyey_greenstein(g: f64, rng: &mut SmallRng) -> f64 {
+    let r = rng.gen::<f64>();
+    if g.abs() < 0.001 {
+        return 1.0 - 2.0 * r;
+    }
+    let sqr_term = (1.0 - g * g) / (1.0 - g + 2.0 * g * r);
+    num::clamp((1.0 + g * g - sqr_term * sqr_term) / (2.0 * g), -1.0, 1.0)
+}
+impl Phase {
+    pub fn value(&self, cos_theta: f64) -> f64 {
+        match *self {
+            Phase::Isotropic => 1.0 / (4.0 * std::f64::consts::PI),
+            Phase::HenyeyGreenstein { g } => henyey_greenstein(g, cos_theta),
+            Phase::DoubleHenyeyGreenstein { g1, g2, weight } => {
+                weight * henyey_greenstein(g1, cos_theta)
+                    + (1.0 - weight) * henyey_greenstein(g2, cos_theta)
+            }
+            Phase::Rayleigh => 3.0 / (16.0 * std::f64::consts::PI) * (1.0 + cos_theta * cos_theta),
+        }
+    }
+    pub fn sample(&self, rng: &mut SmallRng) -> Vec3 {
+        let cos_theta = match *self {
+            Phase::Isotropic => 1.0 - 2.0 * rng.gen::<f64>(),
+            Phase::HenyeyGreenstein { g } => sample_henyey_greenstein(g, rng),
+            Phase::DoubleHenyeyGreenstein { g1, g2, weight } => {
+                if rng.gen::<f64>() < weight {
+                    sample_henyey_greenstein(g1, rng)
+                } else {
+                    sample_henyey_greenstein(g2, rng)
+                }
+            }
+            Phase::Rayleigh => {
+                let q = 8.0 * rng.gen::<f64>() - 4.0;
+                let d = (q * q / 4.0 + 1.0).sqrt();
+                num::clamp((q / 2.0 + d).cbrt() + (q / 2.0 - d).cbrt(), -1.0, 1.0)
+            }
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * std::f64::consts::PI * rng.gen::<f64>();
+        Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+    }
+}
+
+pub struct PhasePDF {
+    pub phase: Phase,
+    pub uvw: ONB,
+}
+impl PhasePDF {
+    pub fn new(phase: Phase, w: Vec3) -> Self {
+        Self {
+            phase,
+            uvw: ONB::build_from_w(w),
+        }
+    }
+}
+impl PDF for PhasePDF {
+    fn value(&self, direction: Vec3) -> f64 {
+        self.phase.value(direction.unit() * self.uvw.w)
+    }
+    fn generate(&self, rng: &mut SmallRng) -> Vec3 {
+        self.uvw.local(self.phase.sample(rng))
+    }
+}
+
 pub struct ObjectPDF<'a, T: Object> {
     pub object: &'a T,
     pub origin: Vec3,
@@ -92,3 +168,42 @@ impl<'a, T1: PDF, T2: PDF> PDF for MixturePDF<'a, T1, T2> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn integrate(phase: Phase) -> f64 {
+        let n = 100000;
+        let mut ans = 0.0;
+        for i in 0..n {
+            let cos_theta = -1.0 + 2.0 * (i as f64 + 0.5) / n as f64;
+            ans += phase.value(cos_theta) * 2.0 / n as f64;
+        }
+        ans * 2.0 * std::f64::consts::PI
+    }
+
+    #[test]
+    fn test_phase_normalized() {
+        assert!((integrate(Phase::Isotropic) - 1.0).abs() < 1e-6);
+        assert!((integrate(Phase::HenyeyGreenstein { g: 0.7 }) - 1.0).abs() < 1e-3);
+        assert!((integrate(Phase::HenyeyGreenstein { g: -0.3 }) - 1.0).abs() < 1e-3);
+        assert!((integrate(Phase::Rayleigh) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_phase_sample_mean_cosine() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let n = 200000;
+        let mut hg = 0.0;
+        let mut rayleigh = 0.0;
+        for _ in 0..n {
+            hg += Phase::HenyeyGreenstein { g: 0.6 }.sample(&mut rng).z;
+            let z = Phase::Rayleigh.sample(&mut rng).z;
+            rayleigh += z * z;
+        }
+        assert!((hg / n as f64 - 0.6).abs() < 0.01);
+        assert!((rayleigh / n as f64 - 0.4).abs() < 0.01);
+    }
+}
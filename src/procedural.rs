@@ -0,0 +1,327 @@
+pub use crate::texture::*;
+
+fn hash(i: i64, j: i64, k: i64, seed: u32) -> u32 {
+    let mut h = (i as u32).wrapping_mul(0x8da6_b343)
+        ^ (j as u32).wrapping_mul(0xd816_3841)
+        ^ (k as u32).wrapping_mul(0xcb1a_b31f)
+        ^ seed.wrapping_mul(0x9e37_79b9);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^ (h >> 16)
+}
+fn hash_unit(i: i64, j: i64, k: i64, seed: u32) -> f64 {
+    hash(i, j, k, seed) as f64 / (u32::MAX as f64 + 1.0)
+}
+
+const GRADIENTS: [(f64, f64, f64); 12] = [
+    (1.0, 1.0, 0.0),
+    (-1.0, 1.0, 0.0),
+    (1.0, -1.0, 0.0),
+    (-1.0, -1.0, 0.0),
+    (1.0, 0.0, 1.0),
+    (-1.0, 0.0, 1.0),
+    (1.0, 0.0, -1.0),
+    (-1.0, 0.0, -1.0),
+    (0.0, 1.0, 1.0),
+    (0.0, -1.0, 1.0),
+    (0.0, 1.0, -1.0),
+    (0.0, -1.0, -1.0),
+];
+
+pub fn simplex3(p: Vec3, seed: u32) -> f64 {
+    let f3 = 1.0 / 3.0;
+    let g3 = 1.0 / 6.0;
+    let s = (p.x + p.y + p.z) * f3;
+    let (i, j, k) = ((p.x + s).floor(), (p.y + s).floor(), (p.z + s).floor());
+    let t = (i + j + k) * g3;
+    let d0 = Vec3::new(p.x - (i - t), p.y - (j - t), p.z - (k - t));
+    let (o1, o2) = if d0.x >= d0.y {
+        if d0.y >= d0.z {
+            ((1, 0, 0), (1, 1, 0))
+        } else if d0.x >= d0.z {
+            ((1, 0, 0), (1, 0, 1))
+        } else {
+            ((0, 0, 1), (1, 0, 1))
+        }
+    } else if d0.y < d0.z {
+        ((0, 0, 1), (0, 1, 1))
+    } else if d0.x < d0.z {
+        ((0, 1, 0), (0, 1, 1))
+    } else {
+        ((0, 1, 0), (1, 1, 0))
+    };
+    let (i, j, k) = (i as i64, j as i64, k as i64);
+    let mut accum = 0.0;
+    for &(a, b, c, offset) in [
+        (0, 0, 0, 0.0),
+        (o1.0, o1.1, o1.2, g3),
+        (o2.0, o2.1, o2.2, 2.0 * g3),
+        (1, 1, 1, 3.0 * g3),
+    ]
+    .iter()
+    {
+        let d = d0 - Vec3::new(a as f64, b as f64, c as f64) + Vec3::ones() * offset;
+        let t = 0.6 - d.squared_length();
+        if t > 0.0 {
+            let g = GRADIENTS[(hash(i + a, j + b, k + c, seed) % 12) as usize];
+            accum += t.powi(4) * (Vec3::new(g.0, g.1, g.2) * d);
+        }
+    }
+    32.0 * accum
+}
+
+pub fn worley3(p: Vec3, seed: u32) -> (f64, f64) {
+    let (i, j, k) = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+    let (mut f1, mut f2) = (f64::MAX, f64::MAX);
+    for a in i - 1..=i + 1 {
+        for b in j - 1..=j + 1 {
+            for c in k - 1..=k + 1 {
+                let feature = Vec3::new(
+                    a as f64 + hash_unit(a, b, c, seed),
+                    b as f64 + hash_unit(a, b, c, seed ^ 0x68e3_1da4),
+                    c as f64 + hash_unit(a, b, c, seed ^ 0xb529_7a4d),
+                );
+                let d = (feature - p).length();
+                if d < f1 {
+                    f2 = f1;
+                    f1 = d;
+                } else if d < f2 {
+                    f2 = d;
+                }
+            }
+        }
+    }
+    (f1, f2)
+}
+
+#[derive(Clone)]
+pub struct SimplexTexture {
+    pub scale: f64,
+    pub seed: u32,
+}
+impl Texture for SimplexTexture {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        Vec3::ones() * (0.5 + 0.5 * simplex3(p * self.scale, self.seed))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum WorleyFeature {
+    F1,
+    F2,
+    F2MinusF1,
+}
+
+#[derive(Clone)]
+pub struct WorleyTexture {
+    pub scale: f64,
+    pub seed: u32,
+    pub feature: WorleyFeature,
+}
+impl Texture for WorleyTexture {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        let (f1, f2) = worley3(p * self.scale, self.seed);
+        let x = match self.feature {
+            WorleyFeature::F1 => f1,
+            WorleyFeature::F2 => f2,
+            WorleyFeature::F2MinusF1 => f2 - f1,
+        };
+        Vec3::ones() * num::clamp(x, 0.0, 1.0)
+    }
+}
+
+#[derive(Clone)]
+pub struct Fbm<T: Texture> {
+    pub basis: T,
+    pub octaves: usize,
+    pub lacunarity: f64,
+    pub gain: f64,
+}
+impl<T: Texture> Texture for Fbm<T> {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let mut accum = Vec3::zero();
+        let (mut frequency, mut amplitude, mut total) = (1.0, 1.0, 0.0);
+        for _ in 0..self.octaves {
+            accum += self
+                .basis
+                .value(u * frequency, v * frequency, p * frequency)
+                * amplitude;
+            total += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+        if total > 0.0 {
+            accum / total
+        } else {
+            accum
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ColorRamp<T: Texture> {
+    pub input: T,
+    pub stops: Vec<(f64, Vec3)>,
+}
+impl<T: Texture> ColorRamp<T> {
+    pub fn color(&self, x: f64) -> Vec3 {
+        let first = self.stops.first().expect("ColorRamp:no stops");
+        if x <= first.0 {
+            return first.1;
+        }
+        for pair in self.stops.windows(2) {
+            let ((x0, c0), (x1, c1)) = (pair[0], pair[1]);
+            if x <= x1 {
+                let t = if x1 > x0 { (x - x0) / (x1 - x0) } else { 1.0 };
+                return c0 * (1.0 - t) + c1 * t;
+            }
+        }
+        self.stops.last().unwrap().1
+    }
+}
+impl<T: Texture> Texture for ColorRamp<T> {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        self.color(self.input.value(u, v, p).luminance())
+    }
+}
+
+#[derive(Clone)]
+pub struct WoodTexture<T1: Texture, T2: Texture> {
+    pub light: T1,
+    pub dark: T2,
+    pub center: Vec3,
+    pub axis: Vec3,
+    pub ring_spacing: f64,
+    pub distortion: f64,
+    pub seed: u32,
+}
+impl<T1: Texture, T2: Texture> Texture for WoodTexture<T1, T2> {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let axis = self.axis.unit();
+        let q = p - self.center;
+        let along = axis * (q * axis);
+        let r = (q - along).length() / self.ring_spacing;
+        let grain = simplex3((q - along * 0.9) / self.ring_spacing, self.seed);
+        let ring = r + self.distortion * grain;
+        let t = (ring - ring.floor()).powf(3.0);
+        self.light.value(u, v, p) * (1.0 - t) + self.dark.value(u, v, p) * t
+    }
+}
+
+#[derive(Clone)]
+pub struct BrickTexture<T1: Texture, T2: Texture> {
+    pub brick: T1,
+    pub mortar: T2,
+    pub brick_width: f64,
+    pub brick_height: f64,
+    pub mortar_width: f64,
+}
+impl<T1: Texture, T2: Texture> Texture for BrickTexture<T1, T2> {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let y = v / self.brick_height;
+        let row = y.floor();
+        let x = u / self.brick_width + if row as i64 % 2 == 0 { 0.0 } else { 0.5 };
+        let (fx, fy) = (x - x.floor(), y - row);
+        let mx = self.mortar_width / self.brick_width;
+        let my = self.mortar_width / self.brick_height;
+        if fx < mx * 0.5 || fx > 1.0 - mx * 0.5 || fy < my * 0.5 || fy > 1.0 - my * 0.5 {
+            self.mortar.value(u, v, p)
+        } else {
+            self.brick.value(u, v, p)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_procedural_textures() {
+        let mut min = f64::MAX;
+        let mut max = f64::MIN;
+        for i in 0..1000 {
+            let p = Vec3::new(i as f64 * 0.37, i as f64 * 0.11, i as f64 * -0.23);
+            let n = simplex3(p, 7);
+            assert_eq!(n, simplex3(p, 7));
+            min = min.min(n);
+            max = max.max(n);
+            let (f1, f2) = worley3(p, 7);
+            assert!(f1 <= f2);
+        }
+        for &(a, b, c) in [(0, 0, 0), (3, -2, 5), (-4, 1, -1)].iter() {
+            let feature = Vec3::new(
+                a as f64 + hash_unit(a, b, c, 7),
+                b as f64 + hash_unit(a, b, c, 7 ^ 0x68e3_1da4),
+                c as f64 + hash_unit(a, b, c, 7 ^ 0xb529_7a4d),
+            );
+            let (f1, f2) = worley3(feature, 7);
+            assert!(f1 < 1e-12 && f2 > 0.0);
+            let (g1, _) = worley3(feature + Vec3::new(0.01, 0.0, 0.0), 7);
+            assert!(g1 <= 0.01 + 1e-12);
+        }
+        assert!(min > -1.0 && max < 1.0 && max - min > 1.0);
+
+        let ramp = ColorRamp {
+            input: SolidColor {
+                color: Vec3::ones() * 0.75,
+            },
+            stops: vec![(0.5, Vec3::zero()), (1.0, Vec3::new(1.0, 0.0, 0.0))],
+        };
+        assert!((ramp.value(0.0, 0.0, Vec3::zero()) - Vec3::new(0.5, 0.0, 0.0)).length() < 1e-9);
+        let fbm = Fbm {
+            basis: SimplexTexture {
+                scale: 1.0,
+                seed: 1,
+            },
+            octaves: 1,
+            lacunarity: 2.0,
+            gain: 0.5,
+        };
+        let p = Vec3::new(0.3, 0.7, 1.1);
+        assert_eq!(fbm.value(0.0, 0.0, p), fbm.basis.value(0.0, 0.0, p));
+
+        let bricks = BrickTexture {
+            brick: SolidColor {
+                color: Vec3::ones(),
+            },
+            mortar: SolidColor {
+                color: Vec3::zero(),
+            },
+            brick_width: 0.25,
+            brick_height: 0.1,
+            mortar_width: 0.02,
+        };
+        assert_eq!(bricks.value(0.1, 0.05, p), Vec3::ones());
+        assert_eq!(bricks.value(0.1, 0.1, p), Vec3::zero());
+        assert_eq!(bricks.value(0.25, 0.05, p), Vec3::zero());
+        assert_eq!(bricks.value(0.25, 0.15, p), Vec3::ones());
+
+        let wood = WoodTexture {
+            light: SolidColor {
+                color: Vec3::ones(),
+            },
+            dark: SolidColor {
+                color: Vec3::zero(),
+            },
+            center: Vec3::zero(),
+            axis: Vec3::new(2.0, 0.0, 0.0),
+            ring_spacing: 0.25,
+            distortion: 0.0,
+            seed: 3,
+        };
+        let q = Vec3::new(0.3, 0.7, 1.1);
+        let shifted = wood.value(0.0, 0.0, q + Vec3::new(5.0, 0.0, 0.0));
+        assert!((wood.value(0.0, 0.0, q) - shifted).length() < 1e-9);
+        assert!(
+            (wood.value(0.0, 0.0, q) - wood.value(0.0, 0.0, Vec3::new(0.3, 1.1, 0.7))).length()
+                < 1e-9
+        );
+        assert!(
+            (wood.value(0.0, 0.0, q) - wood.value(0.0, 0.0, Vec3::new(1.1, 0.7, 0.3))).length()
+                > 1e-3
+        );
+    }
+}
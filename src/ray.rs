@@ -1,13 +1,27 @@
 pub use crate::vec3::Vec3;
 
+#[derive(Clone, Copy)]
+pub struct RayDifferential {
+    pub rx_ori: Vec3,
+    pub rx_dir: Vec3,
+    pub ry_ori: Vec3,
+    pub ry_dir: Vec3,
+}
+
 pub struct Ray {
     pub ori: Vec3,
     pub dir: Vec3,
     pub time: f64,
+    pub differential: Option<RayDifferential>,
 }
 impl Ray {
     pub fn new(ori: Vec3, dir: Vec3, time: f64) -> Self {
-        Self { ori, dir, time }
+        Self {
+            ori,
+            dir,
+            time,
+            differential: None,
+        }
     }
     pub fn at(&self, t: f64) -> Vec3 {
         self.ori + self.dir * t
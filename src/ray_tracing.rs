@@ -1,18 +1,57 @@
 use image::{ImageBuffer, RgbImage};
 use indicatif::ProgressBar;
 use rand::{rngs::SmallRng, Rng, SeedableRng};
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc};
 use threadpool::ThreadPool;
 
 pub use crate::camera::*;
 pub use crate::objects::*;
 pub use crate::scenes::*;
 
-pub fn ray_color<T: Object>(
+#[allow(clippy::too_many_arguments)]
+fn scatter_color<P: PDF>(
     ray: &Ray,
+    rec: &HitRecord,
+    attenuation: Vec3,
+    pdf: &P,
     world: &ObjectList,
-    background: Vec3,
-    lights: &Option<T>,
+    background: &dyn Background,
+    lights: &LightList,
+    depth: i32,
+    rng: &mut SmallRng,
+) -> Vec3 {
+    let mut direct = Vec3::zero();
+    if let Some((sample, prob)) = lights.sample_delta(rec.p, rng) {
+        let shadow_ray = Ray::new(rec.p, sample.direction, ray.time);
+        if world.hit(&shadow_ray, 0.001, sample.distance).is_none() {
+            direct = Vec3::elemul(attenuation, sample.radiance)
+                * rec.mat_ptr.scattering_pdf(ray, rec, &shadow_ray)
+                / prob;
+        }
+    }
+    let (scattered, pdf_value) = if lights.has_area() {
+        let lights_pdf = ObjectPDF::new(lights, rec.p);
+        let p = MixturePDF::new(&lights_pdf, pdf);
+        let scattered = Ray::new(rec.p, p.generate(rng), ray.time);
+        let pdf_value = p.value(scattered.dir);
+        (scattered, pdf_value)
+    } else {
+        let scattered = Ray::new(rec.p, pdf.generate(rng), ray.time);
+        let pdf_value = pdf.value(scattered.dir);
+        (scattered, pdf_value)
+    };
+    direct
+        + Vec3::elemul(
+            attenuation,
+            ray_color(&scattered, world, background, lights, depth - 1, rng),
+        ) * rec.mat_ptr.scattering_pdf(ray, rec, &scattered)
+            / pdf_value
+}
+pub fn ray_color(
+    ray: &Ray,
+    world: &ObjectList,
+    background: &dyn Background,
+    lights: &LightList,
     depth: i32,
     rng: &mut SmallRng,
 ) -> Vec3 {
@@ -20,27 +59,35 @@ pub fn ray_color<T: Object>(
         return Vec3::zero();
     }
     if let Some(rec) = world.hit(ray, 0.001, f64::MAX) {
-        let emitted = rec.mat_ptr.emitted(ray, &rec, rec.u, rec.v, rec.p);
+        let emitted = rec.mat_ptr.emitted(ray, &rec);
         match rec.mat_ptr.scatter(ray, &rec, rng) {
             Some(ScatterRecord::Diffuse { attenuation, pdf }) => {
-                let (scattered, pdf_value) = if let Some(lights) = lights {
-                    let lights_pdf = ObjectPDF::new(lights, rec.p);
-                    let p = MixturePDF::new(&lights_pdf, &pdf);
-                    let scattered = Ray::new(rec.p, p.generate(rng), ray.time);
-                    let pdf_value = p.value(scattered.dir);
-                    (scattered, pdf_value)
-                } else {
-                    let p = pdf;
-                    let scattered = Ray::new(rec.p, p.generate(rng), ray.time);
-                    let pdf_value = p.value(scattered.dir);
-                    (scattered, pdf_value)
-                };
                 return emitted
-                    + Vec3::elemul(
+                    + scatter_color(
+                        ray,
+                        &rec,
                         attenuation,
-                        ray_color(&scattered, world, background, lights, depth - 1, rng),
-                    ) * rec.mat_ptr.scattering_pdf(ray, &rec, &scattered)
-                        / pdf_value;
+                        &pdf,
+                        world,
+                        background,
+                        lights,
+                        depth,
+                        rng,
+                    );
+            }
+            Some(ScatterRecord::Volume { attenuation, pdf }) => {
+                return emitted
+                    + scatter_color(
+                        ray,
+                        &rec,
+                        attenuation,
+                        &pdf,
+                        world,
+                        background,
+                        lights,
+                        depth,
+                        rng,
+                    );
             }
             Some(ScatterRecord::Specular {
                 attenuation,
@@ -56,7 +103,7 @@ pub fn ray_color<T: Object>(
             }
         }
     }
-    background
+    background.value(ray.dir.unit())
 }
 pub struct ThreadResult {
     pub x: u32,
@@ -76,6 +123,16 @@ pub fn run_ray_tracing() {
     };
 
     let (world, background, cam, lights) = final_scene(image_width as f64 / image_height as f64);
+    let cam: Arc<dyn CameraModel> = match std::env::var("PROJECTION") {
+        Ok(name) => match Projection::parse(&name) {
+            Ok(projection) => projection.build(&cam),
+            Err(message) => {
+                println!("{}, using perspective", message);
+                cam
+            }
+        },
+        Err(_) => cam,
+    };
 
     let mut img: RgbImage = ImageBuffer::new(image_width, image_height);
     let pbar = ProgressBar::new(image_width as u64);
@@ -90,6 +147,7 @@ pub fn run_ray_tracing() {
         let world = world.clone();
         let cam = cam.clone();
         let lights = lights.clone();
+        let background = background.clone();
         let mut rng = SmallRng::from_entropy();
         pool.execute(move || {
             for x in start_x..end_x {
@@ -100,12 +158,19 @@ pub fn run_ray_tracing() {
                         let u = (x as f64 + rng.gen::<f64>()) / (image_width as f64 - 1.0);
                         let v = (y as f64 + rng.gen::<f64>()) / (image_height as f64 - 1.0);
                         color += loop {
-                            let ray = cam.get_ray(u, v, &mut rng);
-                            let rec = ray_color::<ObjectList>(
-                                &ray, &world, background, &lights, 50, &mut rng,
-                            );
+                            let (ray, weight) = match cam.sample_ray(
+                                u,
+                                v,
+                                1.0 / (image_width as f64 - 1.0),
+                                1.0 / (image_height as f64 - 1.0),
+                                &mut rng,
+                            ) {
+                                Some(sample) => sample,
+                                None => break Vec3::zero(),
+                            };
+                            let rec = ray_color(&ray, &world, &*background, &lights, 50, &mut rng);
                             if !(rec.x.is_nan() || rec.y.is_nan() || rec.z.is_nan()) {
-                                break rec;
+                                break rec * weight;
                             }
                         };
                     }
@@ -2,11 +2,16 @@ use rand::{rngs::SmallRng, Rng, SeedableRng};
 use std::sync::Arc;
 
 pub use crate::camera::*;
-pub use crate::objects::*;
+pub use crate::sky::*;
 
 pub fn random_scene(
     aspect_ratio: f64,
-) -> (Arc<ObjectList>, Vec3, Arc<Camera>, Arc<Option<ObjectList>>) {
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
     let mut rng = SmallRng::from_entropy();
     let mut world = ObjectList { objects: vec![] };
     world.add(Box::new(Sphere {
@@ -93,7 +98,7 @@ pub fn random_scene(
     }));
     (
         Arc::new(world),
-        Vec3::new(0.7, 0.8, 1.0),
+        Arc::new(Vec3::new(0.7, 0.8, 1.0)),
         Arc::new(Camera::new(
             Vec3::new(13.0, 2.0, 3.0),
             Vec3::new(0.0, 0.0, 0.0),
@@ -105,14 +110,19 @@ pub fn random_scene(
             0.0,
             1.0,
         )),
-        Arc::new(None),
+        Arc::new(LightList::new()),
     )
 }
 use ray_tracing_codegen::random_scene_static_impl;
 random_scene_static_impl! {}
 pub fn random_scene_static(
     aspect_ratio: f64,
-) -> (Arc<ObjectList>, Vec3, Arc<Camera>, Arc<Option<ObjectList>>) {
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
     let mut world = ObjectList { objects: vec![] };
     world.add(Box::new(Sphere {
         center: Vec3::new(0.0, -1000.0, 0.0),
@@ -153,7 +163,7 @@ pub fn random_scene_static(
     }));
     (
         Arc::new(world),
-        Vec3::new(0.7, 0.8, 1.0),
+        Arc::new(Vec3::new(0.7, 0.8, 1.0)),
         Arc::new(Camera::new(
             Vec3::new(13.0, 2.0, 3.0),
             Vec3::new(0.0, 0.0, 0.0),
@@ -165,12 +175,17 @@ pub fn random_scene_static(
             0.0,
             1.0,
         )),
-        Arc::new(None),
+        Arc::new(LightList::new()),
     )
 }
 pub fn random_scene_light(
     aspect_ratio: f64,
-) -> (Arc<ObjectList>, Vec3, Arc<Camera>, Arc<Option<ObjectList>>) {
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
     let mut rng = SmallRng::from_entropy();
     let mut world = ObjectList { objects: vec![] };
     world.add(Box::new(Sphere {
@@ -316,7 +331,7 @@ pub fn random_scene_light(
     }));
     (
         Arc::new(world),
-        Vec3::zero(),
+        Arc::new(Vec3::zero()),
         Arc::new(Camera::new(
             Vec3::new(6.0, 3.0, 6.0),
             Vec3::new(0.0, 0.0, 0.0),
@@ -328,14 +343,19 @@ pub fn random_scene_light(
             0.0,
             1.0,
         )),
-        Arc::new(None),
+        Arc::new(LightList::new()),
     )
 }
 use ray_tracing_codegen::random_scene_light_static_impl;
 random_scene_light_static_impl! {}
 pub fn random_scene_light_static(
     aspect_ratio: f64,
-) -> (Arc<ObjectList>, Vec3, Arc<Camera>, Arc<Option<ObjectList>>) {
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
     let mut world = ObjectList { objects: vec![] };
     world.add(Box::new(Sphere {
         center: Vec3::new(0.0, -1000.0, 0.0),
@@ -381,7 +401,7 @@ pub fn random_scene_light_static(
     }));
     (
         Arc::new(world),
-        Vec3::zero(),
+        Arc::new(Vec3::zero()),
         Arc::new(Camera::new(
             Vec3::new(6.0, 3.0, 6.0),
             Vec3::new(0.0, 0.0, 0.0),
@@ -393,10 +413,12 @@ pub fn random_scene_light_static(
             0.0,
             1.0,
         )),
-        Arc::new(None),
+        Arc::new(LightList::new()),
     )
 }
-pub fn two_checker_spheres(aspect_ratio: f64) -> (Arc<ObjectList>, Vec3, Arc<Camera>) {
+pub fn two_checker_spheres(
+    aspect_ratio: f64,
+) -> (Arc<ObjectList>, Arc<dyn Background>, Arc<Camera>) {
     let mut world = ObjectList { objects: vec![] };
     world.add(Box::new(Sphere {
         center: Vec3::new(0.0, -10.0, 0.0),
@@ -428,7 +450,7 @@ pub fn two_checker_spheres(aspect_ratio: f64) -> (Arc<ObjectList>, Vec3, Arc<Cam
     }));
     (
         Arc::new(world),
-        Vec3::new(0.7, 0.8, 1.0),
+        Arc::new(Vec3::new(0.7, 0.8, 1.0)),
         Arc::new(Camera::new(
             Vec3::new(13.0, 2.0, 3.0),
             Vec3::new(0.0, 0.0, 0.0),
@@ -442,31 +464,27 @@ pub fn two_checker_spheres(aspect_ratio: f64) -> (Arc<ObjectList>, Vec3, Arc<Cam
         )),
     )
 }
-pub fn two_perlin_spheres(aspect_ratio: f64) -> (Arc<ObjectList>, Vec3, Arc<Camera>) {
+pub fn two_perlin_spheres(
+    aspect_ratio: f64,
+) -> (Arc<ObjectList>, Arc<dyn Background>, Arc<Camera>) {
     let mut world = ObjectList { objects: vec![] };
     world.add(Box::new(Sphere {
         center: Vec3::new(0.0, -1000.0, 0.0),
         radius: 1000.0,
         material: Lambertian {
-            albedo: NoiseTexture {
-                noise: Perlin::new(),
-                scale: 4.0,
-            },
+            albedo: NoiseTexture::new(Perlin::new(), 4.0),
         },
     }));
     world.add(Box::new(Sphere {
         center: Vec3::new(0.0, 2.0, 0.0),
         radius: 2.0,
         material: Lambertian {
-            albedo: NoiseTexture {
-                noise: Perlin::new(),
-                scale: 4.0,
-            },
+            albedo: NoiseTexture::new(Perlin::new(), 4.0),
         },
     }));
     (
         Arc::new(world),
-        Vec3::new(0.7, 0.8, 1.0),
+        Arc::new(Vec3::new(0.7, 0.8, 1.0)),
         Arc::new(Camera::new(
             Vec3::new(13.0, 2.0, 3.0),
             Vec3::new(0.0, 0.0, 0.0),
@@ -480,18 +498,21 @@ pub fn two_perlin_spheres(aspect_ratio: f64) -> (Arc<ObjectList>, Vec3, Arc<Came
         )),
     )
 }
-pub fn earth(aspect_ratio: f64) -> (Arc<ObjectList>, Vec3, Arc<Camera>) {
+pub fn earth(aspect_ratio: f64) -> (Arc<ObjectList>, Arc<dyn Background>, Arc<Camera>) {
     let mut world = ObjectList { objects: vec![] };
     world.add(Box::new(Sphere {
         center: Vec3::new(0.0, 0.0, 0.0),
         radius: 2.0,
         material: Lambertian {
-            albedo: ImageTexture::new("images/earthmap.jpg"),
+            albedo: ImageTexture {
+                wrap_v: WrapMode::Clamp,
+                ..ImageTexture::new("images/earthmap.jpg")
+            },
         },
     }));
     (
         Arc::new(world),
-        Vec3::new(0.7, 0.8, 1.0),
+        Arc::new(Vec3::new(0.7, 0.8, 1.0)),
         Arc::new(Camera::new(
             Vec3::new(13.0, 2.0, 3.0),
             Vec3::new(0.0, 0.0, 0.0),
@@ -505,15 +526,71 @@ pub fn earth(aspect_ratio: f64) -> (Arc<ObjectList>, Vec3, Arc<Camera>) {
         )),
     )
 }
-pub fn simple_light(aspect_ratio: f64) -> (Arc<ObjectList>, Vec3, Arc<Camera>) {
+pub fn simple_light(aspect_ratio: f64) -> (Arc<ObjectList>, Arc<dyn Background>, Arc<Camera>) {
+    let mut world = ObjectList { objects: vec![] };
+    world.add(Box::new(Sphere {
+        center: Vec3::new(0.0, -1000.0, 0.0),
+        radius: 1000.0,
+        material: Lambertian {
+            albedo: NoiseTexture::new(Perlin::new(), 4.0),
+        },
+    }));
+    world.add(Box::new(Sphere {
+        center: Vec3::new(0.0, 2.0, 0.0),
+        radius: 2.0,
+        material: Lambertian {
+            albedo: NoiseTexture::new(Perlin::new(), 4.0),
+        },
+    }));
+    world.add(Box::new(RectXY {
+        x1: 3.0,
+        x2: 5.0,
+        y1: 1.0,
+        y2: 3.0,
+        k: -2.0,
+        face: 1.0,
+        material: DiffuseLight {
+            emit: SolidColor {
+                color: Vec3::new(4.0, 4.0, 4.0),
+            },
+        },
+    }));
+    (
+        Arc::new(world),
+        Arc::new(Vec3::zero()),
+        Arc::new(Camera::new(
+            Vec3::new(26.0, 3.0, 6.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            20.0,
+            aspect_ratio,
+            0.0,
+            26.7,
+            0.0,
+            1.0,
+        )),
+    )
+}
+pub fn point_lights(
+    aspect_ratio: f64,
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
     let mut world = ObjectList { objects: vec![] };
     world.add(Box::new(Sphere {
         center: Vec3::new(0.0, -1000.0, 0.0),
         radius: 1000.0,
         material: Lambertian {
-            albedo: NoiseTexture {
-                noise: Perlin::new(),
-                scale: 4.0,
+            albedo: CheckerTexture {
+                odd: SolidColor {
+                    color: Vec3::new(0.2, 0.3, 0.1),
+                },
+                even: SolidColor {
+                    color: Vec3::new(0.9, 0.9, 0.9),
+                },
             },
         },
     }));
@@ -521,44 +598,1565 @@ pub fn simple_light(aspect_ratio: f64) -> (Arc<ObjectList>, Vec3, Arc<Camera>) {
         center: Vec3::new(0.0, 2.0, 0.0),
         radius: 2.0,
         material: Lambertian {
-            albedo: NoiseTexture {
-                noise: Perlin::new(),
-                scale: 4.0,
+            albedo: SolidColor {
+                color: Vec3::new(0.7, 0.3, 0.2),
+            },
+        },
+    }));
+    world.add(Box::new(Sphere {
+        center: Vec3::new(4.0, 1.0, -3.0),
+        radius: 1.0,
+        material: Metal {
+            albedo: Vec3::new(0.8, 0.8, 0.8),
+            fuzz: 0.2,
+        },
+    }));
+    let mut lights = LightList::new();
+    lights.add(Box::new(DirectionalLight::new(
+        Vec3::new(-1.0, -2.0, -0.5),
+        Vec3::new(1.0, 0.9, 0.7),
+        10.0,
+    )));
+    lights.add(Box::new(PointLight {
+        position: Vec3::new(3.0, 5.0, 4.0),
+        intensity: Vec3::new(10.0, 20.0, 30.0),
+    }));
+    lights.add_sphere(
+        &mut world,
+        Vec3::new(-4.0, 1.0, 3.0),
+        0.5,
+        Vec3::new(8.0, 4.0, 2.0),
+    );
+    (
+        Arc::new(world),
+        Arc::new(Vec3::new(0.05, 0.07, 0.1)),
+        Arc::new(Camera::new(
+            Vec3::new(26.0, 6.0, 6.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            20.0,
+            aspect_ratio,
+            0.0,
+            26.7,
+            0.0,
+            1.0,
+        )),
+        Arc::new(lights),
+    )
+}
+pub fn many_lights(
+    aspect_ratio: f64,
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
+    let mut rng = SmallRng::from_entropy();
+    let mut world = ObjectList { objects: vec![] };
+    world.add(Box::new(Sphere {
+        center: Vec3::new(0.0, -1000.0, 0.0),
+        radius: 1000.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.5, 0.5, 0.5),
+            },
+        },
+    }));
+    world.add(Box::new(Sphere {
+        center: Vec3::new(0.0, 1.0, 0.0),
+        radius: 1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.8, 0.8, 0.8),
+            },
+        },
+    }));
+    let mut lights = LightList::new();
+    for a in -10..10 {
+        for b in -10..10 {
+            let center = Vec3::new(
+                a as f64 + 0.5 * rng.gen::<f64>(),
+                rng.gen_range(0.1, 0.5),
+                b as f64 + 0.5 * rng.gen::<f64>(),
+            );
+            if (center - Vec3::new(0.0, 1.0, 0.0)).length() < 1.3 {
+                continue;
+            }
+            let color = Vec3::random(0.2, 1.0, &mut rng) * rng.gen_range(1.0, 20.0);
+            lights.add_sphere(&mut world, center, 0.08, color);
+        }
+    }
+    lights.use_bvh();
+    (
+        Arc::new(world),
+        Arc::new(Vec3::zero()),
+        Arc::new(Camera::new(
+            Vec3::new(8.0, 5.0, 8.0),
+            Vec3::new(0.0, 0.5, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            aspect_ratio,
+            0.0,
+            11.0,
+            0.0,
+            1.0,
+        )),
+        Arc::new(lights),
+    )
+}
+pub fn environment_light(
+    aspect_ratio: f64,
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
+    let mut world = ObjectList { objects: vec![] };
+    world.add(Box::new(Sphere {
+        center: Vec3::new(0.0, -1000.0, 0.0),
+        radius: 1000.0,
+        material: Lambertian {
+            albedo: CheckerTexture {
+                odd: SolidColor {
+                    color: Vec3::new(0.2, 0.3, 0.1),
+                },
+                even: SolidColor {
+                    color: Vec3::new(0.9, 0.9, 0.9),
+                },
+            },
+        },
+    }));
+    world.add(Box::new(Sphere {
+        center: Vec3::new(0.0, 1.0, 0.0),
+        radius: 1.0,
+        material: Dielectric { ref_idx: 1.5 },
+    }));
+    world.add(Box::new(Sphere {
+        center: Vec3::new(-4.0, 1.0, 0.0),
+        radius: 1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.4, 0.2, 0.1),
+            },
+        },
+    }));
+    world.add(Box::new(Sphere {
+        center: Vec3::new(4.0, 1.0, 0.0),
+        radius: 1.0,
+        material: Metal {
+            albedo: Vec3::new(0.7, 0.6, 0.5),
+            fuzz: 0.0,
+        },
+    }));
+    let environment = Arc::new(EnvironmentMap::new("images/sky.hdr", 0.0, 1.0, 10.0));
+    let mut lights = LightList::new();
+    lights.add(Box::new(environment.clone()));
+    (
+        Arc::new(world),
+        environment,
+        Arc::new(Camera::new(
+            Vec3::new(13.0, 2.0, 3.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            20.0,
+            aspect_ratio,
+            0.0,
+            10.0,
+            0.0,
+            1.0,
+        )),
+        Arc::new(lights),
+    )
+}
+pub fn physical_sky(
+    aspect_ratio: f64,
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
+    let mut world = ObjectList { objects: vec![] };
+    world.add(Box::new(Sphere {
+        center: Vec3::new(0.0, -1000.0, 0.0),
+        radius: 1000.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.5, 0.5, 0.5),
+            },
+        },
+    }));
+    world.add(Box::new(Sphere {
+        center: Vec3::new(0.0, 1.0, 0.0),
+        radius: 1.0,
+        material: Dielectric { ref_idx: 1.5 },
+    }));
+    world.add(Box::new(Sphere {
+        center: Vec3::new(-4.0, 1.0, 0.0),
+        radius: 1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.4, 0.2, 0.1),
+            },
+        },
+    }));
+    world.add(Box::new(Sphere {
+        center: Vec3::new(4.0, 1.0, 0.0),
+        radius: 1.0,
+        material: Metal {
+            albedo: Vec3::new(0.7, 0.6, 0.5),
+            fuzz: 0.0,
+        },
+    }));
+    let sky = Arc::new(PreethamSky::new(
+        Vec3::new(-0.6, 0.35, -0.4),
+        3.0,
+        0.04,
+        10.0,
+    ));
+    let mut lights = LightList::new();
+    lights.add(Box::new(sky.clone()));
+    (
+        Arc::new(world),
+        sky,
+        Arc::new(Camera::new(
+            Vec3::new(13.0, 2.0, 3.0),
+            Vec3::new(0.0, 1.5, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            30.0,
+            aspect_ratio,
+            0.0,
+            10.0,
+            0.0,
+            1.0,
+        )),
+        Arc::new(lights),
+    )
+}
+pub fn parametric_shapes(
+    aspect_ratio: f64,
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
+    let mut world = ObjectList { objects: vec![] };
+    world.add(Box::new(Quad {
+        q: Vec3::new(-20.0, 0.0, -20.0),
+        u: Vec3::new(0.0, 0.0, 40.0),
+        v: Vec3::new(40.0, 0.0, 0.0),
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.5, 0.5, 0.5),
+            },
+        },
+    }));
+    world.add(Box::new(Cylinder::new(
+        Vec3::new(-3.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        0.8,
+        2.0,
+        Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.7, 0.2, 0.2),
+            },
+        },
+    )));
+    world.add(Box::new(Cone::new(
+        Vec3::new(0.0, 0.0, -1.5),
+        Vec3::new(0.0, 1.0, 0.0),
+        1.0,
+        2.5,
+        Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.2, 0.3, 0.7),
+            },
+        },
+    )));
+    world.add(Box::new(Torus::new(
+        Vec3::new(3.0, 1.0, 0.0),
+        Vec3::new(0.0, 1.0, 1.0),
+        0.8,
+        0.3,
+        Metal {
+            albedo: Vec3::new(0.8, 0.6, 0.2),
+            fuzz: 0.1,
+        },
+    )));
+    world.add(Box::new(Disk::new(
+        Vec3::new(0.0, 0.8, 2.0),
+        Vec3::new(0.0, 1.0, 1.0),
+        0.8,
+        Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.2, 0.7, 0.3),
+            },
+        },
+    )));
+    let mut lights = LightList::new();
+    lights.add_area(
+        &mut world,
+        Quad {
+            q: Vec3::new(-2.0, 6.0, 2.0),
+            u: Vec3::new(3.0, 0.0, 0.0),
+            v: Vec3::new(0.0, -1.0, 2.0),
+            material: DiffuseLight {
+                emit: SolidColor {
+                    color: Vec3::new(8.0, 8.0, 8.0),
+                },
+            },
+        },
+        emitter_power(Vec3::new(8.0, 8.0, 8.0), 3.0 * 5.0_f64.sqrt()),
+    );
+    lights.add_area(
+        &mut world,
+        Disk::new(
+            Vec3::new(4.0, 4.0, -3.0),
+            Vec3::new(-1.0, -1.0, 1.0),
+            0.6,
+            DiffuseLight {
+                emit: SolidColor {
+                    color: Vec3::new(10.0, 6.0, 3.0),
+                },
+            },
+        ),
+        emitter_power(Vec3::new(10.0, 6.0, 3.0), std::f64::consts::PI * 0.6 * 0.6),
+    );
+    (
+        Arc::new(world),
+        Arc::new(Vec3::new(0.02, 0.02, 0.03)),
+        Arc::new(Camera::new(
+            Vec3::new(0.0, 4.0, 12.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            35.0,
+            aspect_ratio,
+            0.0,
+            12.0,
+            0.0,
+            1.0,
+        )),
+        Arc::new(lights),
+    )
+}
+pub fn procedural_textures(
+    aspect_ratio: f64,
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
+    let mut world = ObjectList { objects: vec![] };
+    world.add(Box::new(Quad {
+        q: Vec3::new(-10.0, 0.0, -10.0),
+        u: Vec3::new(0.0, 0.0, 20.0),
+        v: Vec3::new(20.0, 0.0, 0.0),
+        material: Lambertian {
+            albedo: BrickTexture {
+                brick: ColorRamp {
+                    input: Fbm {
+                        basis: SimplexTexture {
+                            scale: 4.0,
+                            seed: 1,
+                        },
+                        octaves: 4,
+                        lacunarity: 2.0,
+                        gain: 0.5,
+                    },
+                    stops: vec![
+                        (0.3, Vec3::new(0.35, 0.1, 0.06)),
+                        (0.7, Vec3::new(0.6, 0.25, 0.15)),
+                    ],
+                },
+                mortar: SolidColor {
+                    color: Vec3::new(0.7, 0.7, 0.65),
+                },
+                brick_width: 0.04,
+                brick_height: 0.015,
+                mortar_width: 0.002,
+            },
+        },
+    }));
+    world.add(Box::new(Sphere {
+        center: Vec3::new(-3.3, 1.0, 0.0),
+        radius: 1.0,
+        material: Lambertian {
+            albedo: WoodTexture {
+                light: SolidColor {
+                    color: Vec3::new(0.75, 0.5, 0.25),
+                },
+                dark: SolidColor {
+                    color: Vec3::new(0.35, 0.18, 0.07),
+                },
+                center: Vec3::new(-3.6, 0.0, 0.4),
+                axis: Vec3::new(0.0, 1.0, 0.0),
+                ring_spacing: 0.25,
+                distortion: 0.2,
+                seed: 2,
+            },
+        },
+    }));
+    world.add(Box::new(Sphere {
+        center: Vec3::new(-1.1, 1.0, 0.0),
+        radius: 1.0,
+        material: Lambertian {
+            albedo: ColorRamp {
+                input: Fbm {
+                    basis: SimplexTexture {
+                        scale: 1.5,
+                        seed: 3,
+                    },
+                    octaves: 6,
+                    lacunarity: 2.2,
+                    gain: 0.55,
+                },
+                stops: vec![
+                    (0.2, Vec3::new(0.05, 0.1, 0.3)),
+                    (0.5, Vec3::new(0.9, 0.9, 0.95)),
+                    (0.8, Vec3::new(0.3, 0.35, 0.6)),
+                ],
+            },
+        },
+    }));
+    world.add(Box::new(Sphere {
+        center: Vec3::new(1.1, 1.0, 0.0),
+        radius: 1.0,
+        material: Lambertian {
+            albedo: ColorRamp {
+                input: WorleyTexture {
+                    scale: 4.0,
+                    seed: 4,
+                    feature: WorleyFeature::F2MinusF1,
+                },
+                stops: vec![
+                    (0.0, Vec3::new(0.1, 0.02, 0.02)),
+                    (0.15, Vec3::new(0.8, 0.3, 0.1)),
+                    (0.5, Vec3::new(0.95, 0.85, 0.4)),
+                ],
+            },
+        },
+    }));
+    world.add(Box::new(Sphere {
+        center: Vec3::new(3.3, 1.0, 0.0),
+        radius: 1.0,
+        material: Lambertian {
+            albedo: Fbm {
+                basis: WorleyTexture {
+                    scale: 3.0,
+                    seed: 5,
+                    feature: WorleyFeature::F1,
+                },
+                octaves: 3,
+                lacunarity: 2.0,
+                gain: 0.5,
+            },
+        },
+    }));
+    let mossy_bricks: Arc<dyn Texture> = Arc::new(MixTexture {
+        a: BrickTexture {
+            brick: SolidColor {
+                color: Vec3::new(0.45, 0.42, 0.4),
+            },
+            mortar: SolidColor {
+                color: Vec3::new(0.2, 0.2, 0.2),
+            },
+            brick_width: 0.4,
+            brick_height: 0.2,
+            mortar_width: 0.03,
+        },
+        b: SolidColor {
+            color: Vec3::new(0.15, 0.35, 0.1),
+        },
+        factor: ColorRamp {
+            input: Fbm {
+                basis: SimplexTexture {
+                    scale: 2.0,
+                    seed: 6,
+                },
+                octaves: 4,
+                lacunarity: 2.0,
+                gain: 0.5,
+            },
+            stops: vec![(0.45, Vec3::zero()), (0.6, Vec3::ones())],
+        },
+    });
+    world.add(Box::new(Cuboid::new(
+        Vec3::new(-0.8, 0.0, -3.5),
+        Vec3::new(0.8, 2.4, -1.9),
+        Lambertian {
+            albedo: Arc::new(TriplanarTexture {
+                texture: UvTransform {
+                    texture: mossy_bricks,
+                    scale: (1.0, 1.0),
+                    offset: (0.1, 0.0),
+                    rotation: 0.0,
+                },
+                scale: 1.0,
+                sharpness: 4.0,
+            }) as Arc<dyn Texture>,
+        },
+    )));
+    let mut lights = LightList::new();
+    lights.add_area(
+        &mut world,
+        Quad {
+            q: Vec3::new(-2.0, 6.0, 2.0),
+            u: Vec3::new(4.0, 0.0, 0.0),
+            v: Vec3::new(0.0, 0.0, 2.0),
+            material: DiffuseLight {
+                emit: SolidColor {
+                    color: Vec3::new(6.0, 6.0, 6.0),
+                },
+            },
+        },
+        emitter_power(Vec3::new(6.0, 6.0, 6.0), 8.0),
+    );
+    (
+        Arc::new(world),
+        Arc::new(Vec3::new(0.3, 0.35, 0.45)),
+        Arc::new(Camera::new(
+            Vec3::new(0.0, 3.0, 10.0),
+            Vec3::new(0.0, 0.8, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            aspect_ratio,
+            0.0,
+            10.0,
+            0.0,
+            1.0,
+        )),
+        Arc::new(lights),
+    )
+}
+pub fn cutout_fence(
+    aspect_ratio: f64,
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
+    let mut world = ObjectList { objects: vec![] };
+    world.add(Box::new(Quad {
+        q: Vec3::new(-10.0, 0.0, -10.0),
+        u: Vec3::new(0.0, 0.0, 20.0),
+        v: Vec3::new(20.0, 0.0, 0.0),
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.6, 0.6, 0.55),
+            },
+        },
+    }));
+    world.add(Box::new(Sphere {
+        center: Vec3::new(0.0, 1.0, -1.5),
+        radius: 1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.7, 0.2, 0.2),
+            },
+        },
+    }));
+    world.add(Box::new(Quad {
+        q: Vec3::new(-3.0, 0.0, 0.0),
+        u: Vec3::new(6.0, 0.0, 0.0),
+        v: Vec3::new(0.0, 2.5, 0.0),
+        material: OpacityMask {
+            material: Metal {
+                albedo: Vec3::new(0.6, 0.6, 0.65),
+                fuzz: 0.3,
+            },
+            opacity: BrickTexture {
+                brick: SolidColor {
+                    color: Vec3::zero(),
+                },
+                mortar: SolidColor {
+                    color: Vec3::ones(),
+                },
+                brick_width: 1.0 / 24.0,
+                brick_height: 1.0 / 10.0,
+                mortar_width: 0.006,
+            },
+        },
+    }));
+    for i in 0..3 {
+        world.add(Box::new(Quad {
+            q: Vec3::new(-2.8 + 1.9 * i as f64, 0.0, 1.0 + 0.3 * i as f64),
+            u: Vec3::new(1.4, 0.0, 0.4),
+            v: Vec3::new(0.0, 1.6, 0.0),
+            material: OpacityMask {
+                material: Lambertian {
+                    albedo: ColorRamp {
+                        input: SimplexTexture {
+                            scale: 3.0,
+                            seed: i,
+                        },
+                        stops: vec![
+                            (0.3, Vec3::new(0.1, 0.3, 0.05)),
+                            (0.7, Vec3::new(0.4, 0.6, 0.1)),
+                        ],
+                    },
+                },
+                opacity: ColorRamp {
+                    input: WorleyTexture {
+                        scale: 3.0,
+                        seed: i,
+                        feature: WorleyFeature::F1,
+                    },
+                    stops: vec![(0.5, Vec3::ones()), (0.55, Vec3::zero())],
+                },
+            },
+        }));
+    }
+    let mut lights = LightList::new();
+    lights.add_sphere(
+        &mut world,
+        Vec3::new(-2.0, 6.0, 5.0),
+        0.3,
+        Vec3::new(300.0, 300.0, 300.0),
+    );
+    (
+        Arc::new(world),
+        Arc::new(Vec3::new(0.2, 0.25, 0.35)),
+        Arc::new(Camera::new(
+            Vec3::new(1.5, 2.5, 9.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            aspect_ratio,
+            0.0,
+            9.0,
+            0.0,
+            1.0,
+        )),
+        Arc::new(lights),
+    )
+}
+pub fn bokeh(
+    aspect_ratio: f64,
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
+    let mut world = ObjectList { objects: vec![] };
+    world.add(Box::new(Quad {
+        q: Vec3::new(-20.0, 0.0, -40.0),
+        u: Vec3::new(0.0, 0.0, 60.0),
+        v: Vec3::new(40.0, 0.0, 0.0),
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.3, 0.3, 0.3),
+            },
+        },
+    }));
+    world.add(Box::new(Sphere {
+        center: Vec3::new(0.0, 1.0, 0.0),
+        radius: 1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.7, 0.3, 0.2),
+            },
+        },
+    }));
+    let mut rng = SmallRng::seed_from_u64(48);
+    for i in 0..40 {
+        let color = Vec3::new(
+            rng.gen_range(0.3, 1.0),
+            rng.gen_range(0.3, 1.0),
+            rng.gen_range(0.3, 1.0),
+        );
+        world.add(Box::new(Sphere {
+            center: Vec3::new(
+                rng.gen_range(-12.0, 12.0),
+                rng.gen_range(0.5, 6.0),
+                -25.0 + i as f64 * 0.1,
+            ),
+            radius: 0.08,
+            material: DiffuseLight {
+                emit: SolidColor {
+                    color: color * 60.0,
+                },
+            },
+        }));
+    }
+    let mut lights = LightList::new();
+    lights.add_sphere(
+        &mut world,
+        Vec3::new(3.0, 6.0, 4.0),
+        0.5,
+        Vec3::new(40.0, 40.0, 40.0),
+    );
+    let cam = Camera::new(
+        Vec3::new(0.0, 1.5, 6.0),
+        Vec3::new(0.0, 1.5, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        40.0,
+        aspect_ratio,
+        0.25,
+        10.0,
+        0.0,
+        1.0,
+    )
+    .with_aperture(Aperture::Image(ApertureImage::new(
+        "images/aperture_star.png",
+    )))
+    .with_cat_eye(0.6)
+    .focus_on(&world, 0.5, 0.5);
+    (
+        Arc::new(world),
+        Arc::new(Vec3::new(0.01, 0.01, 0.02)),
+        Arc::new(cam),
+        Arc::new(lights),
+    )
+}
+#[allow(clippy::type_complexity)]
+pub fn lens_camera(
+    aspect_ratio: f64,
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<dyn CameraModel>,
+    Arc<LightList>,
+) {
+    let mut world = ObjectList { objects: vec![] };
+    world.add(Box::new(Sphere {
+        center: Vec3::new(0.0, -1000.0, 0.0),
+        radius: 1000.0,
+        material: Lambertian {
+            albedo: CheckerTexture {
+                odd: SolidColor {
+                    color: Vec3::new(0.2, 0.3, 0.1),
+                },
+                even: SolidColor {
+                    color: Vec3::new(0.9, 0.9, 0.9),
+                },
+            },
+        },
+    }));
+    for i in 0..8 {
+        let t = i as f64 / 7.0;
+        world.add(Box::new(Sphere {
+            center: Vec3::new(-0.6 + 1.2 * t, 0.2, 0.5 - 6.0 * t),
+            radius: 0.2,
+            material: Lambertian {
+                albedo: SolidColor {
+                    color: Vec3::new(0.8 - 0.6 * t, 0.3, 0.2 + 0.6 * t),
+                },
+            },
+        }));
+    }
+    let cam = RealisticCamera::new(
+        Vec3::new(0.0, 0.6, 2.0),
+        Vec3::new(0.0, 0.2, -1.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        load_lens("images/dgauss_50mm.lens"),
+        25.0,
+        (Vec3::new(-0.6 + 1.2 * 3.0 / 7.0, 0.2, 0.5 - 18.0 / 7.0) - Vec3::new(0.0, 0.6, 2.0))
+            .length(),
+        43.27,
+        aspect_ratio,
+        0.001,
+        0.0,
+        1.0,
+    );
+    (
+        Arc::new(world),
+        Arc::new(Vec3::new(0.7, 0.8, 1.0)),
+        Arc::new(cam),
+        Arc::new(LightList::new()),
+    )
+}
+pub fn csg_shapes(
+    aspect_ratio: f64,
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
+    let mut world = ObjectList { objects: vec![] };
+    world.add(Box::new(Quad {
+        q: Vec3::new(-20.0, 0.0, -20.0),
+        u: Vec3::new(0.0, 0.0, 40.0),
+        v: Vec3::new(40.0, 0.0, 0.0),
+        material: Lambertian {
+            albedo: CheckerTexture {
+                odd: SolidColor {
+                    color: Vec3::new(0.2, 0.3, 0.1),
+                },
+                even: SolidColor {
+                    color: Vec3::new(0.9, 0.9, 0.9),
+                },
+            },
+        },
+    }));
+    world.add(Box::new(Difference {
+        left: Sphere {
+            center: Vec3::new(-2.5, 1.2, 0.0),
+            radius: 1.2,
+            material: Lambertian {
+                albedo: SolidColor {
+                    color: Vec3::new(0.7, 0.2, 0.2),
+                },
+            },
+        },
+        right: Cuboid::new(
+            Vec3::new(-2.5, 1.2, 0.0),
+            Vec3::new(-1.0, 3.0, 1.5),
+            Lambertian {
+                albedo: SolidColor {
+                    color: Vec3::new(0.9, 0.8, 0.3),
+                },
+            },
+        ),
+    }));
+    world.add(Box::new(Intersection {
+        left: Sphere {
+            center: Vec3::new(0.0, 1.2, -1.6),
+            radius: 2.0,
+            material: Dielectric { ref_idx: 1.5 },
+        },
+        right: Sphere {
+            center: Vec3::new(0.0, 1.2, 1.6),
+            radius: 2.0,
+            material: Dielectric { ref_idx: 1.5 },
+        },
+    }));
+    world.add(Box::new(Difference {
+        left: Union {
+            left: Sphere {
+                center: Vec3::new(2.5, 1.0, 0.0),
+                radius: 1.0,
+                material: Metal {
+                    albedo: Vec3::new(0.8, 0.8, 0.9),
+                    fuzz: 0.05,
+                },
+            },
+            right: Cylinder::new(
+                Vec3::new(2.5, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                0.6,
+                2.4,
+                Metal {
+                    albedo: Vec3::new(0.8, 0.6, 0.2),
+                    fuzz: 0.1,
+                },
+            ),
+        },
+        right: Cylinder::new(
+            Vec3::new(2.5, 1.0, -2.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            0.4,
+            4.0,
+            Lambertian {
+                albedo: SolidColor {
+                    color: Vec3::new(0.2, 0.3, 0.7),
+                },
+            },
+        ),
+    }));
+    let mut lights = LightList::new();
+    lights.add_sphere(
+        &mut world,
+        Vec3::new(0.0, 8.0, 4.0),
+        1.5,
+        Vec3::new(6.0, 6.0, 6.0),
+    );
+    (
+        Arc::new(world),
+        Arc::new(Vec3::new(0.3, 0.4, 0.5)),
+        Arc::new(Camera::new(
+            Vec3::new(0.0, 3.0, 10.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            aspect_ratio,
+            0.0,
+            10.0,
+            0.0,
+            1.0,
+        )),
+        Arc::new(lights),
+    )
+}
+pub fn sdf_shapes(
+    aspect_ratio: f64,
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
+    let mut world = ObjectList { objects: vec![] };
+    world.add(Box::new(Quad {
+        q: Vec3::new(-20.0, 0.0, -20.0),
+        u: Vec3::new(0.0, 0.0, 40.0),
+        v: Vec3::new(40.0, 0.0, 0.0),
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.5, 0.5, 0.5),
+            },
+        },
+    }));
+    world.add(Box::new(SdfObject::new(
+        SmoothUnion {
+            left: SdfSphere {
+                center: Vec3::new(-3.0, 1.0, 0.0),
+                radius: 0.9,
+            },
+            right: SmoothUnion {
+                left: SdfSphere {
+                    center: Vec3::new(-2.2, 1.8, 0.2),
+                    radius: 0.6,
+                },
+                right: SdfTorus {
+                    center: Vec3::new(-3.0, 0.3, 0.0),
+                    major_radius: 1.0,
+                    minor_radius: 0.25,
+                },
+                k: 0.4,
+            },
+            k: 0.4,
+        },
+        Aabb {
+            min: Vec3::new(-4.5, 0.0, -1.5),
+            max: Vec3::new(-1.4, 2.6, 1.5),
+        },
+        Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.7, 0.2, 0.2),
+            },
+        },
+    )));
+    world.add(Box::new(SdfObject::new(
+        Twist {
+            sdf: Round {
+                sdf: SdfBox {
+                    center: Vec3::new(0.0, 1.5, 0.0),
+                    half_size: Vec3::new(0.5, 1.4, 0.5),
+                },
+                radius: 0.1,
+            },
+            center: Vec3::new(0.0, 1.5, 0.0),
+            rate: 1.2,
+        },
+        Aabb {
+            min: Vec3::new(-1.0, 0.0, -1.0),
+            max: Vec3::new(1.0, 3.0, 1.0),
+        },
+        Metal {
+            albedo: Vec3::new(0.8, 0.6, 0.2),
+            fuzz: 0.1,
+        },
+    )));
+    world.add(Box::new(SdfObject::new(
+        Repetition {
+            sdf: SdfSphere {
+                center: Vec3::new(0.0, 0.25, 0.0),
+                radius: 0.2,
+            },
+            period: Vec3::new(0.6, 0.0, 0.6),
+        },
+        Aabb {
+            min: Vec3::new(1.5, 0.0, -1.5),
+            max: Vec3::new(4.5, 0.5, 1.5),
+        },
+        Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.2, 0.3, 0.7),
+            },
+        },
+    )));
+    let mut lights = LightList::new();
+    lights.add_sphere(
+        &mut world,
+        Vec3::new(0.0, 8.0, 4.0),
+        1.5,
+        Vec3::new(6.0, 6.0, 6.0),
+    );
+    (
+        Arc::new(world),
+        Arc::new(Vec3::new(0.3, 0.4, 0.5)),
+        Arc::new(Camera::new(
+            Vec3::new(0.0, 4.0, 10.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            aspect_ratio,
+            0.0,
+            10.0,
+            0.0,
+            1.0,
+        )),
+        Arc::new(lights),
+    )
+}
+pub fn subdivision_surfaces(
+    aspect_ratio: f64,
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
+    let mut world = ObjectList { objects: vec![] };
+    world.add(Box::new(Quad {
+        q: Vec3::new(-20.0, 0.0, -20.0),
+        u: Vec3::new(0.0, 0.0, 40.0),
+        v: Vec3::new(40.0, 0.0, 0.0),
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.5, 0.5, 0.5),
+            },
+        },
+    }));
+    world.add(Box::new(
+        Mesh::load_bpt(
+            "models/wave.bpt",
+            4,
+            Lambertian {
+                albedo: CheckerTexture {
+                    odd: SolidColor {
+                        color: Vec3::new(0.2, 0.3, 0.7),
+                    },
+                    even: SolidColor {
+                        color: Vec3::new(0.9, 0.9, 0.9),
+                    },
+                },
+            },
+        )
+        .transformed(1.0, Vec3::new(-2.0, 1.0, 0.0)),
+    ));
+    world.add(Box::new(
+        Mesh::load_subdivision_obj(
+            "models/cage.obj",
+            3,
+            Metal {
+                albedo: Vec3::new(0.8, 0.6, 0.2),
+                fuzz: 0.1,
+            },
+        )
+        .transformed(1.0, Vec3::new(0.5, 0.0, -0.5)),
+    ));
+    let mut lights = LightList::new();
+    lights.add_sphere(
+        &mut world,
+        Vec3::new(0.0, 8.0, 4.0),
+        1.5,
+        Vec3::new(6.0, 6.0, 6.0),
+    );
+    (
+        Arc::new(world),
+        Arc::new(Vec3::new(0.3, 0.4, 0.5)),
+        Arc::new(Camera::new(
+            Vec3::new(0.0, 5.0, 9.0),
+            Vec3::new(0.0, 0.8, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            aspect_ratio,
+            0.0,
+            10.0,
+            0.0,
+            1.0,
+        )),
+        Arc::new(lights),
+    )
+}
+pub fn surface_detail(
+    aspect_ratio: f64,
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
+    let mut world = ObjectList { objects: vec![] };
+    world.add(Box::new(Quad {
+        q: Vec3::new(-20.0, 0.0, -20.0),
+        u: Vec3::new(0.0, 0.0, 40.0),
+        v: Vec3::new(40.0, 0.0, 0.0),
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.5, 0.5, 0.5),
+            },
+        },
+    }));
+    world.add(Box::new(Sphere {
+        center: Vec3::new(-2.5, 1.0, 0.0),
+        radius: 1.0,
+        material: NormalMap {
+            material: Lambertian {
+                albedo: SolidColor {
+                    color: Vec3::new(0.7, 0.2, 0.2),
+                },
+            },
+            map: ImageTexture::load("images/studs_normal.png", ColorSpace::Linear),
+            strength: 1.0,
+        },
+    }));
+    world.add(Box::new(Sphere {
+        center: Vec3::new(0.0, 1.0, 0.0),
+        radius: 1.0,
+        material: BumpMap {
+            material: RoughMetal {
+                albedo: Vec3::new(0.8, 0.6, 0.2),
+                fuzz: NoiseTexture {
+                    mode: NoiseMode::Turbulence,
+                    strength: 0.3,
+                    ..NoiseTexture::new(Perlin::with_seed(11), 2.0)
+                },
+            },
+            height: NoiseTexture {
+                mode: NoiseMode::Gradient,
+                ..NoiseTexture::new(Perlin::with_seed(7), 8.0)
+            },
+            scale: 0.02,
+        },
+    }));
+    world.add(Box::new(
+        Mesh::load_subdivision_obj(
+            "models/cage.obj",
+            4,
+            Lambertian {
+                albedo: SolidColor {
+                    color: Vec3::new(0.2, 0.3, 0.7),
+                },
+            },
+        )
+        .displaced(&NoiseTexture::new(Perlin::new(), 12.0), 0.08)
+        .transformed(0.8, Vec3::new(1.5, 0.3, -0.4)),
+    ));
+    let mut lights = LightList::new();
+    lights.add_sphere(
+        &mut world,
+        Vec3::new(-2.0, 8.0, 4.0),
+        1.5,
+        Vec3::new(6.0, 6.0, 6.0),
+    );
+    (
+        Arc::new(world),
+        Arc::new(Vec3::new(0.3, 0.4, 0.5)),
+        Arc::new(Camera::new(
+            Vec3::new(0.0, 3.0, 9.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            aspect_ratio,
+            0.0,
+            10.0,
+            0.0,
+            1.0,
+        )),
+        Arc::new(lights),
+    )
+}
+pub fn cornell_box(
+    aspect_ratio: f64,
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
+    let mut world = ObjectList { objects: vec![] };
+    world.add(Box::new(RectYZ {
+        y1: 0.0,
+        y2: 555.0,
+        z1: 0.0,
+        z2: 555.0,
+        k: 555.0,
+        face: -1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.12, 0.45, 0.15),
+            },
+        },
+    }));
+    world.add(Box::new(RectYZ {
+        y1: 0.0,
+        y2: 555.0,
+        z1: 0.0,
+        z2: 555.0,
+        k: 0.0,
+        face: 1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.65, 0.05, 0.05),
+            },
+        },
+    }));
+    world.add(Box::new(RectXZ {
+        x1: 0.0,
+        x2: 555.0,
+        z1: 0.0,
+        z2: 555.0,
+        k: 0.0,
+        face: 1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.73, 0.73, 0.73),
+            },
+        },
+    }));
+    world.add(Box::new(RectXZ {
+        x1: 0.0,
+        x2: 555.0,
+        z1: 0.0,
+        z2: 555.0,
+        k: 555.0,
+        face: -1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.73, 0.73, 0.73),
+            },
+        },
+    }));
+    world.add(Box::new(RectXY {
+        x1: 0.0,
+        x2: 555.0,
+        y1: 0.0,
+        y2: 555.0,
+        k: 555.0,
+        face: -1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.73, 0.73, 0.73),
+            },
+        },
+    }));
+    world.add(Box::new(Translate::new(
+        RotateY::new(
+            Cuboid::new(
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(165.0, 330.0, 165.0),
+                Lambertian {
+                    albedo: SolidColor {
+                        color: Vec3::new(0.73, 0.73, 0.73),
+                    },
+                },
+            ),
+            15.0,
+        ),
+        Vec3::new(265.0, 0.0, 295.0),
+    )));
+    let light_power = emitter_power(Vec3::new(15.0, 15.0, 15.0), 130.0 * 105.0);
+    let mut lights = LightList::new();
+    lights.add_area(
+        &mut world,
+        RectXZ {
+            x1: 213.0,
+            x2: 343.0,
+            z1: 227.0,
+            z2: 332.0,
+            k: 554.0,
+            face: -1.0,
+            material: DiffuseLight {
+                emit: SolidColor {
+                    color: Vec3::new(15.0, 15.0, 15.0),
+                },
+            },
+        },
+        light_power,
+    );
+    world.add(Box::new(Translate::new(
+        Sphere {
+            center: Vec3::new(0.0, 90.0, 0.0),
+            radius: 90.0,
+            material: Dielectric { ref_idx: 1.5 },
+        },
+        Vec3::new(190.0, 0.0, 190.0),
+    )));
+    (
+        Arc::new(world),
+        Arc::new(Vec3::zero()),
+        Arc::new(Camera::new(
+            Vec3::new(278.0, 278.0, -800.0),
+            Vec3::new(278.0, 278.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            aspect_ratio,
+            0.0,
+            28.3,
+            0.0,
+            1.0,
+        )),
+        Arc::new(lights),
+    )
+}
+pub fn cornell_mesh_light(
+    aspect_ratio: f64,
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
+    let mut world = ObjectList { objects: vec![] };
+    world.add(Box::new(RectYZ {
+        y1: 0.0,
+        y2: 555.0,
+        z1: 0.0,
+        z2: 555.0,
+        k: 555.0,
+        face: -1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.12, 0.45, 0.15),
+            },
+        },
+    }));
+    world.add(Box::new(RectYZ {
+        y1: 0.0,
+        y2: 555.0,
+        z1: 0.0,
+        z2: 555.0,
+        k: 0.0,
+        face: 1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.65, 0.05, 0.05),
+            },
+        },
+    }));
+    world.add(Box::new(RectXZ {
+        x1: 0.0,
+        x2: 555.0,
+        z1: 0.0,
+        z2: 555.0,
+        k: 0.0,
+        face: 1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.73, 0.73, 0.73),
+            },
+        },
+    }));
+    world.add(Box::new(RectXZ {
+        x1: 0.0,
+        x2: 555.0,
+        z1: 0.0,
+        z2: 555.0,
+        k: 555.0,
+        face: -1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.73, 0.73, 0.73),
+            },
+        },
+    }));
+    world.add(Box::new(RectXY {
+        x1: 0.0,
+        x2: 555.0,
+        y1: 0.0,
+        y2: 555.0,
+        k: 555.0,
+        face: -1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.73, 0.73, 0.73),
+            },
+        },
+    }));
+    world.add(Box::new(Translate::new(
+        RotateY::new(
+            Cuboid::new(
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(165.0, 330.0, 165.0),
+                Lambertian {
+                    albedo: SolidColor {
+                        color: Vec3::new(0.73, 0.73, 0.73),
+                    },
+                },
+            ),
+            15.0,
+        ),
+        Vec3::new(265.0, 0.0, 295.0),
+    )));
+    let mesh = Mesh::load_obj(
+        "models/icosahedron.obj",
+        DiffuseLight {
+            emit: SolidColor {
+                color: Vec3::new(5.0, 4.0, 2.5),
+            },
+        },
+    )
+    .transformed(70.0, Vec3::new(278.0, 420.0, 278.0));
+    let light_power = emitter_power(Vec3::new(5.0, 4.0, 2.5), mesh.area);
+    let mut lights = LightList::new();
+    lights.add_area(&mut world, mesh, light_power);
+    (
+        Arc::new(world),
+        Arc::new(Vec3::zero()),
+        Arc::new(Camera::new(
+            Vec3::new(278.0, 278.0, -800.0),
+            Vec3::new(278.0, 278.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            aspect_ratio,
+            0.0,
+            28.3,
+            0.0,
+            1.0,
+        )),
+        Arc::new(lights),
+    )
+}
+pub fn cornell_subsurface(
+    aspect_ratio: f64,
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
+    let mut world = ObjectList { objects: vec![] };
+    world.add(Box::new(RectYZ {
+        y1: 0.0,
+        y2: 555.0,
+        z1: 0.0,
+        z2: 555.0,
+        k: 555.0,
+        face: -1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.12, 0.45, 0.15),
+            },
+        },
+    }));
+    world.add(Box::new(RectYZ {
+        y1: 0.0,
+        y2: 555.0,
+        z1: 0.0,
+        z2: 555.0,
+        k: 0.0,
+        face: 1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.65, 0.05, 0.05),
+            },
+        },
+    }));
+    world.add(Box::new(RectXZ {
+        x1: 0.0,
+        x2: 555.0,
+        z1: 0.0,
+        z2: 555.0,
+        k: 0.0,
+        face: 1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.73, 0.73, 0.73),
+            },
+        },
+    }));
+    world.add(Box::new(RectXZ {
+        x1: 0.0,
+        x2: 555.0,
+        z1: 0.0,
+        z2: 555.0,
+        k: 555.0,
+        face: -1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.73, 0.73, 0.73),
+            },
+        },
+    }));
+    world.add(Box::new(RectXY {
+        x1: 0.0,
+        x2: 555.0,
+        y1: 0.0,
+        y2: 555.0,
+        k: 555.0,
+        face: -1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.73, 0.73, 0.73),
             },
         },
     }));
-    world.add(Box::new(RectXY {
-        x1: 3.0,
-        x2: 5.0,
-        y1: 1.0,
-        y2: 3.0,
-        k: -2.0,
-        face: 1.0,
-        material: DiffuseLight {
-            emit: SolidColor {
-                color: Vec3::new(4.0, 4.0, 4.0),
+    world.add(Box::new(Subsurface::new(
+        Translate::new(
+            RotateY::new(
+                Cuboid::new(
+                    Vec3::new(0.0, 0.0, 0.0),
+                    Vec3::new(165.0, 330.0, 165.0),
+                    Lambertian {
+                        albedo: SolidColor {
+                            color: Vec3::new(0.73, 0.73, 0.73),
+                        },
+                    },
+                ),
+                15.0,
+            ),
+            Vec3::new(265.0, 0.0, 295.0),
+        ),
+        NoiseTexture::new(Perlin::new(), 0.05),
+        20.0,
+        1.5,
+        0.0,
+    )));
+    world.add(Box::new(Subsurface::new(
+        Sphere {
+            center: Vec3::new(190.0, 90.0, 190.0),
+            radius: 90.0,
+            material: Dielectric { ref_idx: 1.4 },
+        },
+        SolidColor {
+            color: Vec3::new(0.95, 0.7, 0.45),
+        },
+        15.0,
+        1.4,
+        0.2,
+    )));
+    let mut lights = LightList::new();
+    lights.add_area(
+        &mut world,
+        RectXZ {
+            x1: 213.0,
+            x2: 343.0,
+            z1: 227.0,
+            z2: 332.0,
+            k: 554.0,
+            face: -1.0,
+            material: DiffuseLight {
+                emit: SolidColor {
+                    color: Vec3::new(15.0, 15.0, 15.0),
+                },
             },
         },
-    }));
+        emitter_power(Vec3::new(15.0, 15.0, 15.0), 130.0 * 105.0),
+    );
     (
         Arc::new(world),
-        Vec3::zero(),
+        Arc::new(Vec3::zero()),
         Arc::new(Camera::new(
-            Vec3::new(26.0, 3.0, 6.0),
-            Vec3::new(0.0, 2.0, 0.0),
+            Vec3::new(278.0, 278.0, -800.0),
+            Vec3::new(278.0, 278.0, 0.0),
             Vec3::new(0.0, 1.0, 0.0),
-            20.0,
+            40.0,
             aspect_ratio,
             0.0,
-            26.7,
+            28.3,
             0.0,
             1.0,
         )),
+        Arc::new(lights),
     )
 }
-pub fn cornell_box(
+pub fn cornell_spotlights(
     aspect_ratio: f64,
-) -> (Arc<ObjectList>, Vec3, Arc<Camera>, Arc<Option<ObjectList>>) {
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
     let mut world = ObjectList { objects: vec![] };
     world.add(Box::new(RectYZ {
         y1: 0.0,
@@ -586,19 +2184,6 @@ pub fn cornell_box(
             },
         },
     }));
-    world.add(Box::new(RectXZ {
-        x1: 213.0,
-        x2: 343.0,
-        z1: 227.0,
-        z2: 332.0,
-        k: 554.0,
-        face: -1.0,
-        material: DiffuseLight {
-            emit: SolidColor {
-                color: Vec3::new(15.0, 15.0, 15.0),
-            },
-        },
-    }));
     world.add(Box::new(RectXZ {
         x1: 0.0,
         x2: 555.0,
@@ -653,39 +2238,62 @@ pub fn cornell_box(
         ),
         Vec3::new(265.0, 0.0, 295.0),
     )));
-    world.add(Box::new(Translate::new(
+    let mut lights = LightList::new();
+    lights.add_area(
+        &mut world,
         Sphere {
-            center: Vec3::new(0.0, 90.0, 0.0),
-            radius: 90.0,
-            material: Dielectric { ref_idx: 1.5 },
+            center: Vec3::new(150.0, 450.0, 150.0),
+            radius: 10.0,
+            material: SpotLight::new(
+                SolidColor {
+                    color: Vec3::new(300.0, 260.0, 200.0),
+                },
+                Vec3::new(0.0, -1.0, -0.2),
+                30.0,
+                20.0,
+            ),
         },
-        Vec3::new(190.0, 0.0, 190.0),
-    )));
-    let mut lights = ObjectList { objects: vec![] };
-    lights.add(Box::new(RectXZ {
-        x1: 213.0,
-        x2: 343.0,
-        z1: 227.0,
-        z2: 332.0,
-        k: 554.0,
-        face: -1.0,
-        material: DiffuseLight {
-            emit: SolidColor {
-                color: Vec3::new(15.0, 15.0, 15.0),
-            },
+        emitter_power(Vec3::new(300.0, 260.0, 200.0), 400.0 * std::f64::consts::PI),
+    );
+    lights.add_area(
+        &mut world,
+        RectXZ {
+            x1: 350.0,
+            x2: 410.0,
+            z1: 250.0,
+            z2: 310.0,
+            k: 554.0,
+            face: -1.0,
+            material: IesLight::new(
+                SolidColor {
+                    color: Vec3::new(30.0, 30.0, 30.0),
+                },
+                IesProfile::new("images/downlight.ies"),
+                Vec3::new(0.0, -1.0, 0.0),
+            ),
         },
-    }));
-    lights.add(Box::new(Translate::new(
-        Sphere {
-            center: Vec3::new(0.0, 90.0, 0.0),
-            radius: 90.0,
-            material: Dielectric { ref_idx: 1.5 },
+        emitter_power(Vec3::new(30.0, 30.0, 30.0), 60.0 * 60.0),
+    );
+    lights.add_area(
+        &mut world,
+        RectXY {
+            x1: 80.0,
+            x2: 180.0,
+            y1: 300.0,
+            y2: 330.0,
+            k: 400.0,
+            face: 1.0,
+            material: DiffuseLightTwoSided {
+                emit: SolidColor {
+                    color: Vec3::new(4.0, 6.0, 10.0),
+                },
+            },
         },
-        Vec3::new(190.0, 0.0, 190.0),
-    )));
+        emitter_power(Vec3::new(4.0, 6.0, 10.0), 2.0 * 100.0 * 30.0),
+    );
     (
         Arc::new(world),
-        Vec3::zero(),
+        Arc::new(Vec3::zero()),
         Arc::new(Camera::new(
             Vec3::new(278.0, 278.0, -800.0),
             Vec3::new(278.0, 278.0, 0.0),
@@ -697,10 +2305,10 @@ pub fn cornell_box(
             0.0,
             1.0,
         )),
-        Arc::new(Some(lights)),
+        Arc::new(lights),
     )
 }
-pub fn cornell_smoke(aspect_ratio: f64) -> (Arc<ObjectList>, Vec3, Arc<Camera>) {
+pub fn cornell_smoke(aspect_ratio: f64) -> (Arc<ObjectList>, Arc<dyn Background>, Arc<Camera>) {
     let mut world = ObjectList { objects: vec![] };
     world.add(Box::new(RectYZ {
         y1: 0.0,
@@ -824,7 +2432,145 @@ pub fn cornell_smoke(aspect_ratio: f64) -> (Arc<ObjectList>, Vec3, Arc<Camera>)
     )));
     (
         Arc::new(world),
-        Vec3::zero(),
+        Arc::new(Vec3::zero()),
+        Arc::new(Camera::new(
+            Vec3::new(278.0, 278.0, -800.0),
+            Vec3::new(278.0, 278.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            aspect_ratio,
+            0.0,
+            28.3,
+            0.0,
+            1.0,
+        )),
+    )
+}
+pub fn cornell_cloud(
+    aspect_ratio: f64,
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
+    let mut world = ObjectList { objects: vec![] };
+    world.add(Box::new(RectYZ {
+        y1: 0.0,
+        y2: 555.0,
+        z1: 0.0,
+        z2: 555.0,
+        k: 555.0,
+        face: -1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.12, 0.45, 0.15),
+            },
+        },
+    }));
+    world.add(Box::new(RectYZ {
+        y1: 0.0,
+        y2: 555.0,
+        z1: 0.0,
+        z2: 555.0,
+        k: 0.0,
+        face: 1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.65, 0.05, 0.05),
+            },
+        },
+    }));
+    world.add(Box::new(RectXZ {
+        x1: 0.0,
+        x2: 555.0,
+        z1: 0.0,
+        z2: 555.0,
+        k: 0.0,
+        face: 1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.73, 0.73, 0.73),
+            },
+        },
+    }));
+    world.add(Box::new(RectXZ {
+        x1: 0.0,
+        x2: 555.0,
+        z1: 0.0,
+        z2: 555.0,
+        k: 555.0,
+        face: -1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.73, 0.73, 0.73),
+            },
+        },
+    }));
+    world.add(Box::new(RectXY {
+        x1: 0.0,
+        x2: 555.0,
+        y1: 0.0,
+        y2: 555.0,
+        k: 555.0,
+        face: -1.0,
+        material: Lambertian {
+            albedo: SolidColor {
+                color: Vec3::new(0.73, 0.73, 0.73),
+            },
+        },
+    }));
+    world.add(Box::new(HeterogeneousMedium::new(
+        Sphere {
+            center: Vec3::new(278.0, 278.0, 278.0),
+            radius: 180.0,
+            material: Dielectric { ref_idx: 1.5 },
+        },
+        NoiseTexture::new(Perlin::new(), 0.02),
+        1.0,
+        SolidColor {
+            color: Vec3::new(0.9, 0.9, 0.9),
+        },
+        0.002,
+        0.02,
+    )));
+    world.add(Box::new(ConstantMedium::with_phase(
+        Cuboid::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(555.0, 80.0, 555.0),
+            Lambertian {
+                albedo: SolidColor {
+                    color: Vec3::new(0.73, 0.73, 0.73),
+                },
+            },
+        ),
+        SolidColor {
+            color: Vec3::ones(),
+        },
+        0.002,
+        Phase::HenyeyGreenstein { g: 0.7 },
+    )));
+    let mut lights = LightList::new();
+    lights.add_area(
+        &mut world,
+        RectXZ {
+            x1: 113.0,
+            x2: 443.0,
+            z1: 127.0,
+            z2: 432.0,
+            k: 554.0,
+            face: -1.0,
+            material: DiffuseLight {
+                emit: SolidColor {
+                    color: Vec3::new(7.0, 7.0, 7.0),
+                },
+            },
+        },
+        emitter_power(Vec3::new(7.0, 7.0, 7.0), 330.0 * 305.0),
+    );
+    (
+        Arc::new(world),
+        Arc::new(Vec3::zero()),
         Arc::new(Camera::new(
             Vec3::new(278.0, 278.0, -800.0),
             Vec3::new(278.0, 278.0, 0.0),
@@ -836,11 +2582,17 @@ pub fn cornell_smoke(aspect_ratio: f64) -> (Arc<ObjectList>, Vec3, Arc<Camera>)
             0.0,
             1.0,
         )),
+        Arc::new(lights),
     )
 }
 pub fn final_scene(
     aspect_ratio: f64,
-) -> (Arc<ObjectList>, Vec3, Arc<Camera>, Arc<Option<ObjectList>>) {
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
     let mut rng = SmallRng::from_entropy();
     let mut world = ObjectList { objects: vec![] };
     let mut box1 = ObjectList { objects: vec![] };
@@ -865,19 +2617,6 @@ pub fn final_scene(
         }
     }
     world.add(BvhNode::build(&mut box1.objects, 0.0, 1.0));
-    world.add(Box::new(RectXZ {
-        x1: 123.0,
-        x2: 423.0,
-        z1: 147.0,
-        z2: 412.0,
-        k: 554.0,
-        face: -1.0,
-        material: DiffuseLight {
-            emit: SolidColor {
-                color: Vec3::new(7.0, 7.0, 7.0),
-            },
-        },
-    }));
     world.add(Box::new(MovingSphere {
         center1: Vec3::new(400.0, 400.0, 200.0),
         center2: Vec3::new(400.0, 400.0, 200.0) + Vec3::new(30.0, 0.0, 0.0),
@@ -890,24 +2629,6 @@ pub fn final_scene(
             },
         },
     }));
-    world.add(Box::new(Sphere {
-        center: Vec3::new(260.0, 150.0, 45.0),
-        radius: 50.0,
-        material: Dielectric { ref_idx: 1.5 },
-    }));
-    world.add(Box::new(Sphere {
-        center: Vec3::new(0.0, 150.0, 145.0),
-        radius: 50.0,
-        material: Metal {
-            albedo: Vec3::new(0.8, 0.8, 0.9),
-            fuzz: 1.0,
-        },
-    }));
-    world.add(Box::new(Sphere {
-        center: Vec3::new(360.0, 150.0, 145.0),
-        radius: 70.0,
-        material: Dielectric { ref_idx: 1.5 },
-    }));
     world.add(Box::new(ConstantMedium::new(
         Sphere {
             center: Vec3::new(360.0, 150.0, 145.0),
@@ -934,17 +2655,17 @@ pub fn final_scene(
         center: Vec3::new(400.0, 200.0, 400.0),
         radius: 100.0,
         material: Lambertian {
-            albedo: ImageTexture::new("images/earthmap.jpg"),
+            albedo: ImageTexture {
+                wrap_v: WrapMode::Clamp,
+                ..ImageTexture::new("images/earthmap.jpg")
+            },
         },
     }));
     world.add(Box::new(Sphere {
         center: Vec3::new(220.0, 280.0, 300.0),
         radius: 80.0,
         material: Lambertian {
-            albedo: NoiseTexture {
-                noise: Perlin::new(),
-                scale: 0.1,
-            },
+            albedo: NoiseTexture::new(Perlin::new(), 0.1),
         },
     }));
     let mut box2 = ObjectList { objects: vec![] };
@@ -963,26 +2684,31 @@ pub fn final_scene(
         RotateY::new(Bvh::new(&mut box2.objects, 0.0, 1.0), 15.0),
         Vec3::new(-100.0, 270.0, 395.0),
     )));
-    let mut lights = ObjectList { objects: vec![] };
-    lights.add(Box::new(RectXZ {
-        x1: 123.0,
-        x2: 423.0,
-        z1: 147.0,
-        z2: 412.0,
-        k: 554.0,
-        face: -1.0,
-        material: DiffuseLight {
-            emit: SolidColor {
-                color: Vec3::new(7.0, 7.0, 7.0),
+    let light_power = emitter_power(Vec3::new(7.0, 7.0, 7.0), 300.0 * 265.0);
+    let mut lights = LightList::new();
+    lights.add_area(
+        &mut world,
+        RectXZ {
+            x1: 123.0,
+            x2: 423.0,
+            z1: 147.0,
+            z2: 412.0,
+            k: 554.0,
+            face: -1.0,
+            material: DiffuseLight {
+                emit: SolidColor {
+                    color: Vec3::new(7.0, 7.0, 7.0),
+                },
             },
         },
-    }));
-    lights.add(Box::new(Sphere {
+        light_power,
+    );
+    world.add(Box::new(Sphere {
         center: Vec3::new(260.0, 150.0, 45.0),
         radius: 50.0,
         material: Dielectric { ref_idx: 1.5 },
     }));
-    lights.add(Box::new(Sphere {
+    world.add(Box::new(Sphere {
         center: Vec3::new(0.0, 150.0, 145.0),
         radius: 50.0,
         material: Metal {
@@ -990,14 +2716,14 @@ pub fn final_scene(
             fuzz: 1.0,
         },
     }));
-    lights.add(Box::new(Sphere {
+    world.add(Box::new(Sphere {
         center: Vec3::new(360.0, 150.0, 145.0),
         radius: 70.0,
         material: Dielectric { ref_idx: 1.5 },
     }));
     (
         Arc::new(world),
-        Vec3::zero(),
+        Arc::new(Vec3::zero()),
         Arc::new(Camera::new(
             Vec3::new(478.0, 278.0, -600.0),
             Vec3::new(278.0, 278.0, 0.0),
@@ -1009,31 +2735,23 @@ pub fn final_scene(
             0.0,
             1.0,
         )),
-        Arc::new(Some(lights)),
+        Arc::new(lights),
     )
 }
 use ray_tracing_codegen::final_scene_static_impl;
 final_scene_static_impl! {}
 pub fn final_scene_static(
     aspect_ratio: f64,
-) -> (Arc<ObjectList>, Vec3, Arc<Camera>, Arc<Option<ObjectList>>) {
+) -> (
+    Arc<ObjectList>,
+    Arc<dyn Background>,
+    Arc<Camera>,
+    Arc<LightList>,
+) {
     let mut world = ObjectList { objects: vec![] };
     let (box1, box2) = final_scene_static_bvh();
     world.add(box1);
     world.add(box2);
-    world.add(Box::new(RectXZ {
-        x1: 123.0,
-        x2: 423.0,
-        z1: 147.0,
-        z2: 412.0,
-        k: 554.0,
-        face: -1.0,
-        material: DiffuseLight {
-            emit: SolidColor {
-                color: Vec3::new(7.0, 7.0, 7.0),
-            },
-        },
-    }));
     world.add(Box::new(MovingSphere {
         center1: Vec3::new(400.0, 400.0, 200.0),
         center2: Vec3::new(400.0, 400.0, 200.0) + Vec3::new(30.0, 0.0, 0.0),
@@ -1046,24 +2764,6 @@ pub fn final_scene_static(
             },
         },
     }));
-    world.add(Box::new(Sphere {
-        center: Vec3::new(260.0, 150.0, 45.0),
-        radius: 50.0,
-        material: Dielectric { ref_idx: 1.5 },
-    }));
-    world.add(Box::new(Sphere {
-        center: Vec3::new(0.0, 150.0, 145.0),
-        radius: 50.0,
-        material: Metal {
-            albedo: Vec3::new(0.8, 0.8, 0.9),
-            fuzz: 1.0,
-        },
-    }));
-    world.add(Box::new(Sphere {
-        center: Vec3::new(360.0, 150.0, 145.0),
-        radius: 70.0,
-        material: Dielectric { ref_idx: 1.5 },
-    }));
     world.add(Box::new(ConstantMedium::new(
         Sphere {
             center: Vec3::new(360.0, 150.0, 145.0),
@@ -1090,39 +2790,44 @@ pub fn final_scene_static(
         center: Vec3::new(400.0, 200.0, 400.0),
         radius: 100.0,
         material: Lambertian {
-            albedo: ImageTexture::new("images/earthmap.jpg"),
+            albedo: ImageTexture {
+                wrap_v: WrapMode::Clamp,
+                ..ImageTexture::new("images/earthmap.jpg")
+            },
         },
     }));
     world.add(Box::new(Sphere {
         center: Vec3::new(220.0, 280.0, 300.0),
         radius: 80.0,
         material: Lambertian {
-            albedo: NoiseTexture {
-                noise: Perlin::new(),
-                scale: 0.1,
-            },
+            albedo: NoiseTexture::new(Perlin::new(), 0.1),
         },
     }));
-    let mut lights = ObjectList { objects: vec![] };
-    lights.add(Box::new(RectXZ {
-        x1: 123.0,
-        x2: 423.0,
-        z1: 147.0,
-        z2: 412.0,
-        k: 554.0,
-        face: -1.0,
-        material: DiffuseLight {
-            emit: SolidColor {
-                color: Vec3::new(7.0, 7.0, 7.0),
+    let light_power = emitter_power(Vec3::new(7.0, 7.0, 7.0), 300.0 * 265.0);
+    let mut lights = LightList::new();
+    lights.add_area(
+        &mut world,
+        RectXZ {
+            x1: 123.0,
+            x2: 423.0,
+            z1: 147.0,
+            z2: 412.0,
+            k: 554.0,
+            face: -1.0,
+            material: DiffuseLight {
+                emit: SolidColor {
+                    color: Vec3::new(7.0, 7.0, 7.0),
+                },
             },
         },
-    }));
-    lights.add(Box::new(Sphere {
+        light_power,
+    );
+    world.add(Box::new(Sphere {
         center: Vec3::new(260.0, 150.0, 45.0),
         radius: 50.0,
         material: Dielectric { ref_idx: 1.5 },
     }));
-    lights.add(Box::new(Sphere {
+    world.add(Box::new(Sphere {
         center: Vec3::new(0.0, 150.0, 145.0),
         radius: 50.0,
         material: Metal {
@@ -1130,14 +2835,14 @@ pub fn final_scene_static(
             fuzz: 1.0,
         },
     }));
-    lights.add(Box::new(Sphere {
+    world.add(Box::new(Sphere {
         center: Vec3::new(360.0, 150.0, 145.0),
         radius: 70.0,
         material: Dielectric { ref_idx: 1.5 },
     }));
     (
         Arc::new(world),
-        Vec3::zero(),
+        Arc::new(Vec3::zero()),
         Arc::new(Camera::new(
             Vec3::new(478.0, 278.0, -600.0),
             Vec3::new(278.0, 278.0, 0.0),
@@ -1149,7 +2854,7 @@ pub fn final_scene_static(
             0.0,
             1.0,
         )),
-        Arc::new(Some(lights)),
+        Arc::new(lights),
     )
 }
 use ray_tracing_codegen::scene_from_file_impl;
@@ -0,0 +1,244 @@
+use rand::rngs::SmallRng;
+use std::sync::Arc;
+
+pub use crate::objects::*;
+
+pub trait Sdf: Sync + Send {
+    fn distance(&self, p: Vec3) -> f64;
+}
+impl<T: Sdf + ?Sized> Sdf for Arc<T> {
+    fn distance(&self, p: Vec3) -> f64 {
+        (**self).distance(p)
+    }
+}
+
+pub struct SdfSphere {
+    pub center: Vec3,
+    pub radius: f64,
+}
+impl Sdf for SdfSphere {
+    fn distance(&self, p: Vec3) -> f64 {
+        (p - self.center).length() - self.radius
+    }
+}
+
+pub struct SdfBox {
+    pub center: Vec3,
+    pub half_size: Vec3,
+}
+impl Sdf for SdfBox {
+    fn distance(&self, p: Vec3) -> f64 {
+        let p = p - self.center;
+        let q = Vec3::new(
+            p.x.abs() - self.half_size.x,
+            p.y.abs() - self.half_size.y,
+            p.z.abs() - self.half_size.z,
+        );
+        let outside = Vec3::new(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0)).length();
+        outside + q.x.max(q.y).max(q.z).min(0.0)
+    }
+}
+
+pub struct SdfTorus {
+    pub center: Vec3,
+    pub major_radius: f64,
+    pub minor_radius: f64,
+}
+impl Sdf for SdfTorus {
+    fn distance(&self, p: Vec3) -> f64 {
+        let p = p - self.center;
+        let q = (p.x * p.x + p.z * p.z).sqrt() - self.major_radius;
+        (q * q + p.y * p.y).sqrt() - self.minor_radius
+    }
+}
+
+pub struct SmoothUnion<T1: Sdf, T2: Sdf> {
+    pub left: T1,
+    pub right: T2,
+    pub k: f64,
+}
+impl<T1: Sdf, T2: Sdf> Sdf for SmoothUnion<T1, T2> {
+    fn distance(&self, p: Vec3) -> f64 {
+        let d1 = self.left.distance(p);
+        let d2 = self.right.distance(p);
+        if self.k <= 0.0 {
+            return d1.min(d2);
+        }
+        let h = num::clamp(0.5 + 0.5 * (d2 - d1) / self.k, 0.0, 1.0);
+        d2 * (1.0 - h) + d1 * h - self.k * h * (1.0 - h)
+    }
+}
+
+pub struct Repetition<T: Sdf> {
+    pub sdf: T,
+    pub period: Vec3,
+}
+impl<T: Sdf> Sdf for Repetition<T> {
+    fn distance(&self, p: Vec3) -> f64 {
+        let repeat = |x: f64, period: f64| {
+            if period > 0.0 {
+                x - period * (x / period).round()
+            } else {
+                x
+            }
+        };
+        self.sdf.distance(Vec3::new(
+            repeat(p.x, self.period.x),
+            repeat(p.y, self.period.y),
+            repeat(p.z, self.period.z),
+        ))
+    }
+}
+
+pub struct Twist<T: Sdf> {
+    pub sdf: T,
+    pub center: Vec3,
+    pub rate: f64,
+}
+impl<T: Sdf> Sdf for Twist<T> {
+    fn distance(&self, p: Vec3) -> f64 {
+        let q = p - self.center;
+        let angle = self.rate * q.y;
+        let (sin, cos) = angle.sin_cos();
+        let twisted = Vec3::new(cos * q.x - sin * q.z, q.y, sin * q.x + cos * q.z);
+        let r = (q.x * q.x + q.z * q.z).sqrt();
+        self.sdf.distance(twisted + self.center) / (1.0 + (self.rate * r).powi(2)).sqrt()
+    }
+}
+
+pub struct Round<T: Sdf> {
+    pub sdf: T,
+    pub radius: f64,
+}
+impl<T: Sdf> Sdf for Round<T> {
+    fn distance(&self, p: Vec3) -> f64 {
+        self.sdf.distance(p) - self.radius
+    }
+}
+
+pub struct SdfObject<T1: Sdf, T2: Material> {
+    pub sdf: T1,
+    pub boxx: Aabb,
+    pub material: T2,
+    pub max_steps: usize,
+    pub epsilon: f64,
+}
+impl<T1: Sdf, T2: Material> SdfObject<T1, T2> {
+    pub fn new(sdf: T1, boxx: Aabb, material: T2) -> Self {
+        Self {
+            sdf,
+            boxx,
+            material,
+            max_steps: 256,
+            epsilon: 1e-4,
+        }
+    }
+    pub fn normal(&self, p: Vec3) -> Vec3 {
+        let h = self.epsilon;
+        let dx = Vec3::new(h, 0.0, 0.0);
+        let dy = Vec3::new(0.0, h, 0.0);
+        let dz = Vec3::new(0.0, 0.0, h);
+        Vec3::new(
+            self.sdf.distance(p + dx) - self.sdf.distance(p - dx),
+            self.sdf.distance(p + dy) - self.sdf.distance(p - dy),
+            self.sdf.distance(p + dz) - self.sdf.distance(p - dz),
+        )
+        .unit()
+    }
+}
+impl<T1: Sdf, T2: Material> Object for SdfObject<T1, T2> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (mut t, t_end) = self.boxx.interval(ray, t_min, t_max)?;
+        let speed = ray.dir.length();
+        for _ in 0..self.max_steps {
+            let p = ray.at(t);
+            let d = self.sdf.distance(p).abs();
+            if d < self.epsilon {
+                let normal = self.normal(p);
+                let (u, v) = get_sphere_uv(normal);
+                let (dpdu, dpdv) = sphere_derivatives(normal, 1.0);
+                return Some(HitRecord::new(
+                    ray,
+                    t,
+                    normal,
+                    dpdu,
+                    dpdv,
+                    &self.material,
+                    u,
+                    v,
+                ));
+            }
+            t += d / speed;
+            if t > t_end {
+                break;
+            }
+        }
+        None
+    }
+    fn bounding_box(&self, _t1: f64, _t2: f64) -> Option<Aabb> {
+        Some(self.boxx.clone())
+    }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        volume_pdf_value(self, origin, v)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        volume_random(self, origin, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_sdf_hit() {
+        let sphere = SdfObject::new(
+            SdfSphere {
+                center: Vec3::zero(),
+                radius: 1.0,
+            },
+            Aabb {
+                min: -Vec3::ones(),
+                max: Vec3::ones(),
+            },
+            Dielectric { ref_idx: 1.5 },
+        );
+        let ray = Ray::new(Vec3::new(0.3, 0.2, -5.0), Vec3::new(0.0, 0.0, 2.0), 0.0);
+        let rec = sphere.hit(&ray, 0.001, f64::MAX).unwrap();
+        let z = (1.0 - 0.3 * 0.3 - 0.2 * 0.2_f64).sqrt();
+        assert!((rec.p.z + z).abs() < 1e-3);
+        assert!((rec.normal - Vec3::new(0.3, 0.2, -z)).length() < 1e-3);
+        let rec = sphere.hit(&ray, rec.t + 0.001, f64::MAX).unwrap();
+        assert!((rec.p.z - z).abs() < 1e-3);
+        assert!(rec.normal.z > 0.0);
+        let mut rng = SmallRng::seed_from_u64(0);
+        let origin = Vec3::new(0.0, 0.0, -5.0);
+        for _ in 0..100 {
+            let v = sphere.random(origin, &mut rng);
+            assert!(v.z > 0.0 && sphere.pdf_value(origin, v) > 0.0);
+        }
+        assert_eq!(sphere.pdf_value(origin, Vec3::new(0.0, 0.0, -1.0)), 0.0);
+
+        let blobs = SmoothUnion {
+            left: SdfSphere {
+                center: Vec3::new(-1.0, 0.0, 0.0),
+                radius: 0.8,
+            },
+            right: SdfSphere {
+                center: Vec3::new(1.0, 0.0, 0.0),
+                radius: 0.8,
+            },
+            k: 0.5,
+        };
+        assert!(blobs.distance(Vec3::zero()) < 0.2);
+        let grid = Repetition {
+            sdf: SdfSphere {
+                center: Vec3::zero(),
+                radius: 0.25,
+            },
+            period: Vec3::new(1.0, 0.0, 1.0),
+        };
+        assert!((grid.distance(Vec3::new(3.0, 0.0, -7.0)) + 0.25).abs() < 1e-9);
+    }
+}
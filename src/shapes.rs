@@ -0,0 +1,687 @@
+use rand::{rngs::SmallRng, Rng};
+use std::f64::consts::PI;
+
+pub use crate::objects::*;
+
+fn to_local(uvw: &ONB, a: Vec3) -> Vec3 {
+    Vec3::new(a * uvw.u, a * uvw.v, a * uvw.w)
+}
+fn points_box(points: &[Vec3]) -> Aabb {
+    let mut min = Vec3::new(f64::MAX, f64::MAX, f64::MAX);
+    let mut max = Vec3::new(f64::MIN, f64::MIN, f64::MIN);
+    for p in points {
+        min = Vec3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+        max = Vec3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+    }
+    let delta = Vec3::new(0.0001, 0.0001, 0.0001);
+    Aabb {
+        min: min - delta,
+        max: max + delta,
+    }
+}
+fn local_box(origin: Vec3, uvw: &ONB, min: Vec3, max: Vec3) -> Aabb {
+    let corners: Vec<Vec3> = (0..8)
+        .map(|i| {
+            origin
+                + uvw.local(Vec3::new(
+                    if i & 1 == 0 { min.x } else { max.x },
+                    if i & 2 == 0 { min.y } else { max.y },
+                    if i & 4 == 0 { min.z } else { max.z },
+                ))
+        })
+        .collect();
+    points_box(&corners)
+}
+fn polar_u(p: Vec3) -> f64 {
+    (p.y.atan2(p.x) + PI) / (2.0 * PI)
+}
+fn polar_derivatives(uvw: &ONB, p: Vec3, radius: f64) -> (Vec3, Vec3) {
+    let rho = (p.x * p.x + p.y * p.y).sqrt();
+    let radial = if rho > 0.0 {
+        uvw.local(Vec3::new(p.x, p.y, 0.0)) * (radius / rho)
+    } else {
+        Vec3::zero()
+    };
+    (uvw.local(Vec3::new(-p.y, p.x, 0.0)) * (2.0 * PI), radial)
+}
+fn closest_root(roots: &[(f64, usize)], t_min: f64, t_max: f64) -> Option<(f64, usize)> {
+    roots.iter().filter(|x| x.0 > t_min && x.0 < t_max).fold(
+        None,
+        |ans: Option<(f64, usize)>, &x| match ans {
+            Some(y) if y.0 <= x.0 => Some(y),
+            _ => Some(x),
+        },
+    )
+}
+fn polynomial_value(c: &[f64], t: f64) -> f64 {
+    c.iter().rev().fold(0.0, |ans, &x| ans * t + x)
+}
+fn polynomial_roots(c: &[f64], lo: f64, hi: f64) -> Vec<f64> {
+    if c.len() == 2 {
+        if c[1] == 0.0 {
+            return vec![];
+        }
+        let t = -c[0] / c[1];
+        return if t >= lo && t <= hi { vec![t] } else { vec![] };
+    }
+    let derivative: Vec<f64> = (1..c.len()).map(|i| c[i] * i as f64).collect();
+    let mut points = vec![lo];
+    points.extend(polynomial_roots(&derivative, lo, hi));
+    points.push(hi);
+    let mut ans = vec![];
+    for pair in points.windows(2) {
+        let (mut a, mut b) = (pair[0], pair[1]);
+        let (mut fa, fb) = (polynomial_value(c, a), polynomial_value(c, b));
+        if fa == 0.0 {
+            ans.push(a);
+            continue;
+        }
+        if fa * fb > 0.0 {
+            continue;
+        }
+        for _ in 0..64 {
+            let m = 0.5 * (a + b);
+            let fm = polynomial_value(c, m);
+            if fa * fm <= 0.0 {
+                b = m;
+            } else {
+                a = m;
+                fa = fm;
+            }
+        }
+        ans.push(0.5 * (a + b));
+    }
+    ans
+}
+
+pub struct Quad<T: Material> {
+    pub q: Vec3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub material: T,
+}
+impl<T: Material> Object for Quad<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let n = Vec3::cross(self.u, self.v);
+        let denom = n * ray.dir;
+        if denom.abs() < 1e-12 {
+            return None;
+        }
+        let t = n * (self.q - ray.ori) / denom;
+        if t < t_min || t > t_max {
+            return None;
+        }
+        let planar = ray.at(t) - self.q;
+        let w = n / (n * n);
+        let alpha = w * Vec3::cross(planar, self.v);
+        let beta = w * Vec3::cross(self.u, planar);
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+        Some(HitRecord::new(
+            ray,
+            t,
+            n.unit(),
+            self.u,
+            self.v,
+            &self.material,
+            alpha,
+            beta,
+        ))
+    }
+    fn bounding_box(&self, _t1: f64, _t2: f64) -> Option<Aabb> {
+        Some(points_box(&[
+            self.q,
+            self.q + self.u,
+            self.q + self.v,
+            self.q + self.u + self.v,
+        ]))
+    }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        let area = Vec3::cross(self.u, self.v).length();
+        surface_pdf_value(self, origin, v, area)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        self.q + self.u * rng.gen::<f64>() + self.v * rng.gen::<f64>() - origin
+    }
+}
+
+pub struct Disk<T: Material> {
+    pub center: Vec3,
+    pub radius: f64,
+    pub uvw: ONB,
+    pub material: T,
+}
+impl<T: Material> Disk<T> {
+    pub fn new(center: Vec3, normal: Vec3, radius: f64, material: T) -> Self {
+        Self {
+            center,
+            radius,
+            uvw: ONB::build_from_w(normal),
+            material,
+        }
+    }
+}
+impl<T: Material> Object for Disk<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let denom = self.uvw.w * ray.dir;
+        if denom.abs() < 1e-12 {
+            return None;
+        }
+        let t = self.uvw.w * (self.center - ray.ori) / denom;
+        if t < t_min || t > t_max {
+            return None;
+        }
+        let p = to_local(&self.uvw, ray.at(t) - self.center);
+        let r = (p.x * p.x + p.y * p.y).sqrt();
+        if r > self.radius {
+            return None;
+        }
+        let (dpdu, dpdv) = polar_derivatives(&self.uvw, p, self.radius);
+        Some(HitRecord::new(
+            ray,
+            t,
+            self.uvw.w,
+            dpdu,
+            dpdv,
+            &self.material,
+            polar_u(p),
+            r / self.radius,
+        ))
+    }
+    fn bounding_box(&self, _t1: f64, _t2: f64) -> Option<Aabb> {
+        Some(local_box(
+            self.center,
+            &self.uvw,
+            Vec3::new(-self.radius, -self.radius, 0.0),
+            Vec3::new(self.radius, self.radius, 0.0),
+        ))
+    }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        surface_pdf_value(self, origin, v, PI * self.radius * self.radius)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        let r = self.radius * rng.gen::<f64>().sqrt();
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        self.center + self.uvw.local(Vec3::new(r * phi.cos(), r * phi.sin(), 0.0)) - origin
+    }
+}
+
+pub struct Cylinder<T: Material> {
+    pub base: Vec3,
+    pub radius: f64,
+    pub height: f64,
+    pub uvw: ONB,
+    pub material: T,
+}
+impl<T: Material> Cylinder<T> {
+    pub fn new(base: Vec3, axis: Vec3, radius: f64, height: f64, material: T) -> Self {
+        Self {
+            base,
+            radius,
+            height,
+            uvw: ONB::build_from_w(axis),
+            material,
+        }
+    }
+}
+impl<T: Material> Object for Cylinder<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let o = to_local(&self.uvw, ray.ori - self.base);
+        let d = to_local(&self.uvw, ray.dir);
+        let mut roots = vec![];
+        let a = d.x * d.x + d.y * d.y;
+        let half_b = o.x * d.x + o.y * d.y;
+        let c = o.x * o.x + o.y * o.y - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+        if a > 0.0 && discriminant > 0.0 {
+            for &t in [
+                (-half_b - discriminant.sqrt()) / a,
+                (-half_b + discriminant.sqrt()) / a,
+            ]
+            .iter()
+            {
+                let z = o.z + t * d.z;
+                if z >= 0.0 && z <= self.height {
+                    roots.push((t, 0));
+                }
+            }
+        }
+        if d.z != 0.0 {
+            for (i, &k) in [0.0, self.height].iter().enumerate() {
+                let t = (k - o.z) / d.z;
+                let (x, y) = (o.x + t * d.x, o.y + t * d.y);
+                if x * x + y * y <= self.radius * self.radius {
+                    roots.push((t, i + 1));
+                }
+            }
+        }
+        let (t, side) = closest_root(&roots, t_min, t_max)?;
+        let p = o + d * t;
+        let (dpdu, radial) = polar_derivatives(&self.uvw, p, self.radius);
+        let (normal, dpdv, v) = match side {
+            0 => (
+                Vec3::new(p.x, p.y, 0.0) / self.radius,
+                self.uvw.w * self.height,
+                p.z / self.height,
+            ),
+            1 => (
+                Vec3::new(0.0, 0.0, -1.0),
+                radial,
+                (p.x * p.x + p.y * p.y).sqrt() / self.radius,
+            ),
+            _ => (
+                Vec3::new(0.0, 0.0, 1.0),
+                radial,
+                (p.x * p.x + p.y * p.y).sqrt() / self.radius,
+            ),
+        };
+        Some(HitRecord::new(
+            ray,
+            t,
+            self.uvw.local(normal),
+            dpdu,
+            dpdv,
+            &self.material,
+            polar_u(p),
+            v,
+        ))
+    }
+    fn bounding_box(&self, _t1: f64, _t2: f64) -> Option<Aabb> {
+        Some(local_box(
+            self.base,
+            &self.uvw,
+            Vec3::new(-self.radius, -self.radius, 0.0),
+            Vec3::new(self.radius, self.radius, self.height),
+        ))
+    }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        let area = 2.0 * PI * self.radius * (self.radius + self.height);
+        surface_pdf_value(self, origin, v, area)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        let side = self.height / (self.radius + self.height);
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let p = if rng.gen::<f64>() < side {
+            Vec3::new(
+                self.radius * phi.cos(),
+                self.radius * phi.sin(),
+                self.height * rng.gen::<f64>(),
+            )
+        } else {
+            let r = self.radius * rng.gen::<f64>().sqrt();
+            let z = if rng.gen::<f64>() < 0.5 {
+                0.0
+            } else {
+                self.height
+            };
+            Vec3::new(r * phi.cos(), r * phi.sin(), z)
+        };
+        self.base + self.uvw.local(p) - origin
+    }
+}
+
+pub struct Cone<T: Material> {
+    pub base: Vec3,
+    pub radius: f64,
+    pub height: f64,
+    pub uvw: ONB,
+    pub material: T,
+}
+impl<T: Material> Cone<T> {
+    pub fn new(base: Vec3, axis: Vec3, radius: f64, height: f64, material: T) -> Self {
+        Self {
+            base,
+            radius,
+            height,
+            uvw: ONB::build_from_w(axis),
+            material,
+        }
+    }
+}
+impl<T: Material> Object for Cone<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let o = to_local(&self.uvw, ray.ori - self.base);
+        let d = to_local(&self.uvw, ray.dir);
+        let k = (self.radius / self.height).powi(2);
+        let mut roots = vec![];
+        let hz = self.height - o.z;
+        let a = d.x * d.x + d.y * d.y - k * d.z * d.z;
+        let half_b = o.x * d.x + o.y * d.y + k * hz * d.z;
+        let c = o.x * o.x + o.y * o.y - k * hz * hz;
+        let candidates = if a.abs() < 1e-12 {
+            if half_b.abs() < 1e-12 {
+                vec![]
+            } else {
+                vec![-c / (2.0 * half_b)]
+            }
+        } else {
+            let discriminant = half_b * half_b - a * c;
+            if discriminant < 0.0 {
+                vec![]
+            } else {
+                vec![
+                    (-half_b - discriminant.sqrt()) / a,
+                    (-half_b + discriminant.sqrt()) / a,
+                ]
+            }
+        };
+        for t in candidates {
+            let z = o.z + t * d.z;
+            if z >= 0.0 && z <= self.height {
+                roots.push((t, 0));
+            }
+        }
+        if d.z != 0.0 {
+            let t = -o.z / d.z;
+            let (x, y) = (o.x + t * d.x, o.y + t * d.y);
+            if x * x + y * y <= self.radius * self.radius {
+                roots.push((t, 1));
+            }
+        }
+        let (t, side) = closest_root(&roots, t_min, t_max)?;
+        let p = o + d * t;
+        let (dpdu, radial) = polar_derivatives(&self.uvw, p, self.radius);
+        let (normal, dpdv, v) = if side == 0 {
+            (
+                Vec3::new(p.x, p.y, k * (self.height - p.z)).unit(),
+                self.uvw.w * self.height - radial,
+                p.z / self.height,
+            )
+        } else {
+            (
+                Vec3::new(0.0, 0.0, -1.0),
+                radial,
+                (p.x * p.x + p.y * p.y).sqrt() / self.radius,
+            )
+        };
+        Some(HitRecord::new(
+            ray,
+            t,
+            self.uvw.local(normal),
+            dpdu,
+            dpdv,
+            &self.material,
+            polar_u(p),
+            v,
+        ))
+    }
+    fn bounding_box(&self, _t1: f64, _t2: f64) -> Option<Aabb> {
+        Some(local_box(
+            self.base,
+            &self.uvw,
+            Vec3::new(-self.radius, -self.radius, 0.0),
+            Vec3::new(self.radius, self.radius, self.height),
+        ))
+    }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        let slant = (self.radius * self.radius + self.height * self.height).sqrt();
+        let area = PI * self.radius * (self.radius + slant);
+        surface_pdf_value(self, origin, v, area)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        let slant = (self.radius * self.radius + self.height * self.height).sqrt();
+        let side = slant / (self.radius + slant);
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let s = rng.gen::<f64>().sqrt();
+        let p = if rng.gen::<f64>() < side {
+            Vec3::new(
+                self.radius * s * phi.cos(),
+                self.radius * s * phi.sin(),
+                self.height * (1.0 - s),
+            )
+        } else {
+            Vec3::new(
+                self.radius * s * phi.cos(),
+                self.radius * s * phi.sin(),
+                0.0,
+            )
+        };
+        self.base + self.uvw.local(p) - origin
+    }
+}
+
+pub struct Torus<T: Material> {
+    pub center: Vec3,
+    pub major_radius: f64,
+    pub minor_radius: f64,
+    pub uvw: ONB,
+    pub material: T,
+}
+impl<T: Material> Torus<T> {
+    pub fn new(
+        center: Vec3,
+        axis: Vec3,
+        major_radius: f64,
+        minor_radius: f64,
+        material: T,
+    ) -> Self {
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+            uvw: ONB::build_from_w(axis),
+            material,
+        }
+    }
+}
+impl<T: Material> Object for Torus<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let o = to_local(&self.uvw, ray.ori - self.center);
+        let d = to_local(&self.uvw, ray.dir);
+        let r2 = self.major_radius * self.major_radius;
+        let bound = self.major_radius + self.minor_radius;
+        let a = d * d;
+        let half_b = o * d;
+        let discriminant = half_b * half_b - a * (o * o - bound * bound);
+        if discriminant <= 0.0 {
+            return None;
+        }
+        let lo = ((-half_b - discriminant.sqrt()) / a).max(t_min);
+        let hi = ((-half_b + discriminant.sqrt()) / a).min(t_max);
+        if lo >= hi {
+            return None;
+        }
+        let b = 2.0 * half_b;
+        let c = o * o + r2 - self.minor_radius * self.minor_radius;
+        let coefficients = [
+            c * c - 4.0 * r2 * (o.x * o.x + o.y * o.y),
+            2.0 * b * c - 8.0 * r2 * (o.x * d.x + o.y * d.y),
+            b * b + 2.0 * a * c - 4.0 * r2 * (d.x * d.x + d.y * d.y),
+            2.0 * a * b,
+            a * a,
+        ];
+        let t = polynomial_roots(&coefficients, lo, hi)
+            .into_iter()
+            .find(|&t| t > t_min && t < t_max)?;
+        let p = o + d * t;
+        let ring = Vec3::new(p.x, p.y, 0.0).unit() * self.major_radius;
+        let normal = (p - ring).unit();
+        let rho = (p.x * p.x + p.y * p.y).sqrt();
+        let (dpdu, _) = polar_derivatives(&self.uvw, p, self.major_radius);
+        let ring_direction = ring / self.major_radius;
+        let dpdv = (Vec3::new(0.0, 0.0, normal * ring_direction) - ring_direction * normal.z)
+            * (2.0 * PI * self.minor_radius);
+        Some(HitRecord::new(
+            ray,
+            t,
+            self.uvw.local(normal),
+            dpdu,
+            self.uvw.local(dpdv),
+            &self.material,
+            polar_u(p),
+            (p.z.atan2(rho - self.major_radius) + PI) / (2.0 * PI),
+        ))
+    }
+    fn bounding_box(&self, _t1: f64, _t2: f64) -> Option<Aabb> {
+        let bound = self.major_radius + self.minor_radius;
+        Some(local_box(
+            self.center,
+            &self.uvw,
+            Vec3::new(-bound, -bound, -self.minor_radius),
+            Vec3::new(bound, bound, self.minor_radius),
+        ))
+    }
+    fn pdf_value(&self, origin: Vec3, v: Vec3) -> f64 {
+        let area = 4.0 * PI * PI * self.major_radius * self.minor_radius;
+        surface_pdf_value(self, origin, v, area)
+    }
+    fn random(&self, origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        let theta = loop {
+            let theta = 2.0 * PI * rng.gen::<f64>();
+            let accept = (self.major_radius + self.minor_radius * theta.cos())
+                / (self.major_radius + self.minor_radius);
+            if rng.gen::<f64>() < accept {
+                break theta;
+            }
+        };
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let rho = self.major_radius + self.minor_radius * theta.cos();
+        let p = Vec3::new(
+            rho * phi.cos(),
+            rho * phi.sin(),
+            self.minor_radius * theta.sin(),
+        );
+        self.center + self.uvw.local(p) - origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn check_pdf(object: &dyn Object, origin: Vec3) {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let n = 100000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            sum += object.pdf_value(origin, random_unit_vector(&mut rng));
+        }
+        let integral = sum / n as f64 * 4.0 * PI;
+        assert!((integral - 1.0).abs() < 0.05);
+        for _ in 0..100 {
+            let v = object.random(origin, &mut rng);
+            assert!(object
+                .hit(&Ray::new(origin, v, 0.0), 0.001, f64::MAX)
+                .is_some());
+        }
+    }
+
+    #[test]
+    fn test_shapes_pdf() {
+        let material = Lambertian {
+            albedo: SolidColor {
+                color: Vec3::ones(),
+            },
+        };
+        let origin = Vec3::new(0.3, 0.4, 3.0);
+        check_pdf(
+            &Quad {
+                q: Vec3::new(-1.0, -1.0, 0.0),
+                u: Vec3::new(2.0, 0.0, 0.5),
+                v: Vec3::new(0.0, 2.0, 0.0),
+                material: material.clone(),
+            },
+            origin,
+        );
+        check_pdf(
+            &Disk::new(
+                Vec3::zero(),
+                Vec3::new(0.2, 0.1, 1.0),
+                1.0,
+                material.clone(),
+            ),
+            origin,
+        );
+        check_pdf(
+            &Cylinder::new(
+                Vec3::new(0.0, -1.0, 0.0),
+                Vec3::new(0.1, 1.0, 0.2),
+                0.8,
+                2.0,
+                material.clone(),
+            ),
+            origin,
+        );
+        check_pdf(
+            &Cone::new(
+                Vec3::new(0.0, -1.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.3),
+                1.0,
+                1.5,
+                material.clone(),
+            ),
+            origin,
+        );
+        check_pdf(
+            &Torus::new(Vec3::zero(), Vec3::new(0.3, 1.0, 0.5), 1.0, 0.3, material),
+            origin,
+        );
+    }
+
+    #[test]
+    fn test_shading_frame() {
+        let material = Lambertian {
+            albedo: SolidColor {
+                color: Vec3::ones(),
+            },
+        };
+        let objects: Vec<Box<dyn Object>> = vec![
+            Box::new(Sphere {
+                center: Vec3::zero(),
+                radius: 1.0,
+                material: material.clone(),
+            }),
+            Box::new(Disk::new(
+                Vec3::zero(),
+                Vec3::new(0.2, 1.0, 0.3),
+                1.0,
+                material.clone(),
+            )),
+            Box::new(Cylinder::new(
+                Vec3::new(0.0, -0.5, 0.0),
+                Vec3::new(0.2, 1.0, 0.3),
+                0.8,
+                1.0,
+                material.clone(),
+            )),
+            Box::new(Cone::new(
+                Vec3::new(0.0, -0.5, 0.0),
+                Vec3::new(0.2, 1.0, 0.3),
+                0.8,
+                1.0,
+                material.clone(),
+            )),
+            Box::new(Torus::new(
+                Vec3::zero(),
+                Vec3::new(0.3, 1.0, 0.5),
+                1.0,
+                0.3,
+                material.clone(),
+            )),
+            Box::new(RotateY::new(
+                Cuboid::new(-Vec3::ones(), Vec3::ones(), material),
+                30.0,
+            )),
+        ];
+        let mut rng = SmallRng::seed_from_u64(0);
+        for object in objects.iter() {
+            let mut hits = 0;
+            for _ in 0..1000 {
+                let origin = random_unit_vector(&mut rng) * 3.0;
+                let ray = Ray::new(origin, random_in_unit_sphere(&mut rng) - origin, 0.0);
+                if let Some(rec) = object.hit(&ray, 0.001, f64::MAX) {
+                    hits += 1;
+                    assert!((rec.normal.length() - 1.0).abs() < 1e-6);
+                    assert!((rec.tangent.length() - 1.0).abs() < 1e-6);
+                    assert!((rec.tangent * rec.normal).abs() < 1e-6);
+                    assert!((rec.dpdu * rec.geometric_normal).abs() < 1e-6 * rec.dpdu.length());
+                    assert!((rec.dpdv * rec.geometric_normal).abs() < 1e-6 * rec.dpdv.length());
+                    assert_eq!(rec.front_face, ray.dir * rec.geometric_normal < 0.0);
+                }
+            }
+            assert!(hits > 0);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use rand::{rngs::SmallRng, Rng};
+use std::f64::consts::PI;
+
+pub use crate::environment::*;
+
+#[derive(Clone)]
+pub struct PreethamSky {
+    pub sun_direction: Vec3,
+    pub turbidity: f64,
+    pub intensity: f64,
+    pub zenith: Vec3,
+    pub perez: [[f64; 5]; 3],
+    pub sun_radiance: Vec3,
+    pub sun_uvw: ONB,
+    pub cos_sun_radius: f64,
+    pub scene_radius: f64,
+}
+impl PreethamSky {
+    pub fn new(sun_direction: Vec3, turbidity: f64, intensity: f64, scene_radius: f64) -> Self {
+        let sun_direction = sun_direction.unit();
+        let t = turbidity;
+        let theta_s = num::clamp(sun_direction.y, 0.0, 1.0).acos();
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_y = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let cubic = |a: [f64; 4]| {
+            a[0] * theta_s * theta_s * theta_s + a[1] * theta_s * theta_s + a[2] * theta_s + a[3]
+        };
+        let zenith_x = t * t * cubic([0.00166, -0.00375, 0.00209, 0.0])
+            + t * cubic([-0.02903, 0.06377, -0.03202, 0.00394])
+            + cubic([0.11693, -0.21196, 0.06052, 0.25886]);
+        let zenith_yy = t * t * cubic([0.00275, -0.00610, 0.00317, 0.0])
+            + t * cubic([-0.04214, 0.08970, -0.04153, 0.00516])
+            + cubic([0.15346, -0.26756, 0.06670, 0.26688]);
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+        let sun_radius: f64 = 0.5;
+        let cos_sun_radius = sun_radius.to_radians().cos();
+        let solid_angle = 2.0 * PI * (1.0 - cos_sun_radius);
+        Self {
+            sun_direction,
+            turbidity,
+            intensity,
+            zenith: Vec3::new(zenith_y.max(0.0), zenith_x, zenith_yy),
+            perez,
+            sun_radiance: Self::sun_transmittance(theta_s, t) * (80.0 * intensity / solid_angle),
+            sun_uvw: ONB::build_from_w(sun_direction),
+            cos_sun_radius,
+            scene_radius,
+        }
+    }
+    fn sun_transmittance(theta_s: f64, turbidity: f64) -> Vec3 {
+        if theta_s >= PI / 2.0 {
+            return Vec3::zero();
+        }
+        let air_mass = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+        let beta = 0.04608 * turbidity - 0.04586;
+        let transmittance = |lambda: f64| {
+            let rayleigh = 0.008735 * lambda.powf(-4.08);
+            let aerosol = beta * lambda.powf(-1.3);
+            (-(rayleigh + aerosol) * air_mass).exp()
+        };
+        Vec3::new(
+            transmittance(0.68),
+            transmittance(0.55),
+            transmittance(0.44),
+        )
+    }
+    fn perez(&self, channel: usize, cos_theta: f64, gamma: f64) -> f64 {
+        let [a, b, c, d, e] = self.perez[channel];
+        (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+    }
+    pub fn sky(&self, direction: Vec3) -> Vec3 {
+        let d = direction.unit();
+        let cos_theta = d.y.max(0.01);
+        let gamma = num::clamp(d * self.sun_direction, -1.0, 1.0).acos();
+        let theta_s = num::clamp(self.sun_direction.y, 0.0, 1.0).acos();
+        let mut yxy = [0.0; 3];
+        for (channel, value) in yxy.iter_mut().enumerate() {
+            let zenith = match channel {
+                0 => self.zenith.x,
+                1 => self.zenith.y,
+                _ => self.zenith.z,
+            };
+            *value =
+                zenith * self.perez(channel, cos_theta, gamma) / self.perez(channel, 1.0, theta_s);
+        }
+        let (luminance, x, y) = (yxy[0] * self.intensity, yxy[1], yxy[2]);
+        if y <= 0.0 {
+            return Vec3::zero();
+        }
+        let xx = x / y * luminance;
+        let zz = (1.0 - x - y) / y * luminance;
+        Vec3::new(
+            (3.2406 * xx - 1.5372 * luminance - 0.4986 * zz).max(0.0),
+            (-0.9689 * xx + 1.8758 * luminance + 0.0415 * zz).max(0.0),
+            (0.0557 * xx - 0.2040 * luminance + 1.0570 * zz).max(0.0),
+        )
+    }
+}
+impl Background for PreethamSky {
+    fn value(&self, direction: Vec3) -> Vec3 {
+        let sky = self.sky(direction);
+        if direction.unit() * self.sun_direction >= self.cos_sun_radius {
+            sky + self.sun_radiance
+        } else {
+            sky
+        }
+    }
+}
+impl Light for PreethamSky {
+    fn power(&self) -> f64 {
+        let solid_angle = 2.0 * PI * (1.0 - self.cos_sun_radius);
+        PI * self.scene_radius * self.scene_radius * self.sun_radiance.luminance() * solid_angle
+    }
+    fn pdf_value(&self, _origin: Vec3, v: Vec3) -> f64 {
+        if v.unit() * self.sun_direction >= self.cos_sun_radius {
+            1.0 / (2.0 * PI * (1.0 - self.cos_sun_radius))
+        } else {
+            0.0
+        }
+    }
+    fn random(&self, _origin: Vec3, rng: &mut SmallRng) -> Vec3 {
+        let z = 1.0 - rng.gen::<f64>() * (1.0 - self.cos_sun_radius);
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let r = (1.0 - z * z).sqrt();
+        self.sun_uvw
+            .local(Vec3::new(r * phi.cos(), r * phi.sin(), z))
+    }
+}
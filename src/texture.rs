@@ -1,11 +1,33 @@
-use image::RgbImage;
+use image::{hdr::HdrDecoder, DynamicImage, GenericImageView, ImageBuffer, Pixel};
 use rand::seq::SliceRandom;
 use rand::{rngs::SmallRng, SeedableRng};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock, RwLock, Weak};
 
 pub use crate::materials::*;
 
 pub trait Texture: Sync + Send {
     fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3;
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, _footprint: (f64, f64)) -> Vec3 {
+        self.value(u, v, p)
+    }
+    fn value_at(&self, rec: &HitRecord) -> Vec3 {
+        self.value_filtered(rec.u, rec.v, rec.p, rec.uv_footprint)
+    }
+}
+impl<T: Texture + ?Sized> Texture for Arc<T> {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        (**self).value(u, v, p)
+    }
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, footprint: (f64, f64)) -> Vec3 {
+        (**self).value_filtered(u, v, p, footprint)
+    }
+    fn value_at(&self, rec: &HitRecord) -> Vec3 {
+        (**self).value_at(rec)
+    }
 }
 
 #[derive(Clone)]
@@ -49,51 +71,570 @@ impl<T1: Texture, T2: Texture> Texture for CheckerTexture<T1, T2> {
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+pub enum NoiseMode {
+    Marble,
+    Turbulence,
+    Gradient,
+}
+
 #[derive(Clone)]
 pub struct NoiseTexture {
     pub noise: Perlin,
     pub scale: f64,
+    pub depth: i32,
+    pub strength: f64,
+    pub axis: Vec3,
+    pub mode: NoiseMode,
+}
+impl NoiseTexture {
+    pub fn new(noise: Perlin, scale: f64) -> Self {
+        Self {
+            noise,
+            scale,
+            depth: 7,
+            strength: 10.0,
+            axis: Vec3::new(0.0, 0.0, 1.0),
+            mode: NoiseMode::Marble,
+        }
+    }
 }
 impl Texture for NoiseTexture {
     fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
-        Vec3::ones() * 0.5 * (1.0 + (self.scale * p.z + 10.0 * self.noise.turb(p, 7)).sin())
+        let p = p * self.scale;
+        Vec3::ones()
+            * match self.mode {
+                NoiseMode::Marble => {
+                    0.5 * (1.0
+                        + (self.axis * p + self.strength * self.noise.turb(p, self.depth)).sin())
+                }
+                NoiseMode::Turbulence => self.strength * self.noise.turb(p, self.depth),
+                NoiseMode::Gradient => 0.5 + 0.5 * self.noise.noise(p),
+            }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    Srgb,
+    Linear,
+}
+impl ColorSpace {
+    pub fn decode(self, c: f64) -> f64 {
+        match self {
+            ColorSpace::Linear => c,
+            ColorSpace::Srgb => {
+                if c <= 0.04045 {
+                    c / 12.92
+                } else {
+                    ((c + 0.055) / 1.055).powf(2.4)
+                }
+            }
+        }
+    }
+}
+
+fn decode_pixels<P: Pixel + 'static>(
+    img: &ImageBuffer<P, Vec<P::Subpixel>>,
+    max: f64,
+    color_space: ColorSpace,
+) -> (Vec<Vec3>, Vec<f64>)
+where
+    P::Subpixel: Into<f64>,
+{
+    img.pixels()
+        .map(|pixel| {
+            let [r, g, b, a] = pixel.to_rgba().0;
+            let channel = |x: P::Subpixel| color_space.decode(x.into() / max);
+            (
+                Vec3::new(channel(r), channel(g), channel(b)),
+                a.into() / max,
+            )
+        })
+        .unzip()
+}
+
+pub fn load_image(filename: &str, color_space: ColorSpace) -> (usize, usize, Vec<Vec3>, Vec<f64>) {
+    if filename.ends_with(".hdr") {
+        let file = File::open(filename).expect("ImageTexture:failed to open file");
+        let decoder =
+            HdrDecoder::new(BufReader::new(file)).expect("ImageTexture:failed to decode file");
+        let metadata = decoder.metadata();
+        let data: Vec<Vec3> = decoder
+            .read_image_hdr()
+            .expect("ImageTexture:failed to decode file")
+            .iter()
+            .map(|x| Vec3::new(x[0] as f64, x[1] as f64, x[2] as f64))
+            .collect();
+        let alpha = vec![1.0; data.len()];
+        return (
+            metadata.width as usize,
+            metadata.height as usize,
+            data,
+            alpha,
+        );
+    }
+    let img = image::open(filename).expect("ImageTexture:failed to open file");
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let (data, alpha) = match &img {
+        DynamicImage::ImageLuma16(x) => decode_pixels(x, 65535.0, color_space),
+        DynamicImage::ImageLumaA16(x) => decode_pixels(x, 65535.0, color_space),
+        DynamicImage::ImageRgb16(x) => decode_pixels(x, 65535.0, color_space),
+        DynamicImage::ImageRgba16(x) => decode_pixels(x, 65535.0, color_space),
+        _ => decode_pixels(&img.to_rgba(), 255.0, color_space),
+    };
+    (width, height, data, alpha)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TextureFilter {
+    Nearest,
+    Bilinear,
+    Bicubic,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum WrapMode {
+    Repeat,
+    Clamp,
+    Mirror,
+}
+impl WrapMode {
+    pub fn apply(self, i: i64, n: usize) -> usize {
+        let n = n as i64;
+        (match self {
+            WrapMode::Repeat => i.rem_euclid(n),
+            WrapMode::Clamp => num::clamp(i, 0, n - 1),
+            WrapMode::Mirror => {
+                let i = i.rem_euclid(2 * n);
+                if i < n {
+                    i
+                } else {
+                    2 * n - 1 - i
+                }
+            }
+        }) as usize
+    }
+}
+
+#[derive(Clone)]
+pub struct MipLevel {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<Vec3>,
+}
+impl MipLevel {
+    pub const TILE: usize = 8;
+
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3>) -> Self {
+        if width == 0 || height == 0 || pixels.len() != width * height {
+            panic!("ImageTexture:expected {} texels", width * height);
+        }
+        let tiles_x = width.div_ceil(Self::TILE);
+        let tiles_y = height.div_ceil(Self::TILE);
+        let mut level = Self {
+            width,
+            height,
+            data: vec![Vec3::zero(); tiles_x * tiles_y * Self::TILE * Self::TILE],
+        };
+        for (i, &pixel) in pixels.iter().enumerate() {
+            let index = level.index(i % width, i / width);
+            level.data[index] = pixel;
+        }
+        level
+    }
+    fn index(&self, x: usize, y: usize) -> usize {
+        let tiles_x = self.width.div_ceil(Self::TILE);
+        let tile = (y / Self::TILE) * tiles_x + x / Self::TILE;
+        tile * Self::TILE * Self::TILE + (y % Self::TILE) * Self::TILE + x % Self::TILE
+    }
+    pub fn get(&self, x: usize, y: usize) -> Vec3 {
+        self.data[self.index(x, y)]
+    }
+    pub fn pyramid(width: usize, height: usize, data: Vec<Vec3>) -> Vec<Self> {
+        let mut levels = vec![MipLevel::new(width, height, data)];
+        while levels.last().unwrap().width > 1 || levels.last().unwrap().height > 1 {
+            let next = levels.last().unwrap().downsample();
+            levels.push(next);
+        }
+        levels
+    }
+    pub fn memory_size(levels: &[MipLevel]) -> usize {
+        levels
+            .iter()
+            .map(|level| level.data.len() * std::mem::size_of::<Vec3>())
+            .sum()
+    }
+    fn downsample(&self) -> Self {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut accum = Vec3::zero();
+                for &(dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)].iter() {
+                    let sx = (2 * x + dx).min(self.width - 1);
+                    let sy = (2 * y + dy).min(self.height - 1);
+                    accum += self.get(sx, sy);
+                }
+                data.push(accum * 0.25);
+            }
+        }
+        Self::new(width, height, data)
     }
 }
 
 #[derive(Clone)]
 pub struct ImageTexture {
-    pub img: RgbImage,
+    pub levels: Arc<Vec<MipLevel>>,
+    pub filter: TextureFilter,
+    pub wrap_u: WrapMode,
+    pub wrap_v: WrapMode,
+    pub mipmap: bool,
 }
 impl ImageTexture {
     pub fn new(filename: &str) -> Self {
+        Self::load(filename, ColorSpace::Srgb)
+    }
+    pub fn load(filename: &str, color_space: ColorSpace) -> Self {
+        TextureCache::global().image(filename, color_space)
+    }
+    pub fn load_alpha(filename: &str) -> Self {
+        let (width, height, _, alpha) = load_image(filename, ColorSpace::Linear);
+        Self::from_data(
+            width,
+            height,
+            alpha.iter().map(|&a| Vec3::ones() * a).collect(),
+        )
+    }
+    pub fn from_data(width: usize, height: usize, data: Vec<Vec3>) -> Self {
+        Self::from_levels(Arc::new(MipLevel::pyramid(width, height, data)))
+    }
+    pub fn from_levels(levels: Arc<Vec<MipLevel>>) -> Self {
         Self {
-            img: image::open(filename)
-                .expect("ImageTexture:failed to open file")
-                .to_rgb(),
+            levels,
+            filter: TextureFilter::Bilinear,
+            wrap_u: WrapMode::Repeat,
+            wrap_v: WrapMode::Repeat,
+            mipmap: true,
+        }
+    }
+    fn texel(&self, level: &MipLevel, x: i64, y: i64) -> Vec3 {
+        let x = self.wrap_u.apply(x, level.width);
+        let y = self.wrap_v.apply(y, level.height);
+        level.get(x, y)
+    }
+    pub fn sample(&self, level: usize, u: f64, v: f64) -> Vec3 {
+        let level = &self.levels[level];
+        let x = u * level.width as f64 - 0.5;
+        let y = (1.0 - v) * level.height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        match self.filter {
+            TextureFilter::Nearest => self.texel(level, x.round() as i64, y.round() as i64),
+            TextureFilter::Bilinear => {
+                (self.texel(level, x0, y0) * (1.0 - fx) + self.texel(level, x0 + 1, y0) * fx)
+                    * (1.0 - fy)
+                    + (self.texel(level, x0, y0 + 1) * (1.0 - fx)
+                        + self.texel(level, x0 + 1, y0 + 1) * fx)
+                        * fy
+            }
+            TextureFilter::Bicubic => {
+                let weights = |t: f64| {
+                    let (t2, t3) = (t * t, t * t * t);
+                    [
+                        0.5 * (-t3 + 2.0 * t2 - t),
+                        0.5 * (3.0 * t3 - 5.0 * t2 + 2.0),
+                        0.5 * (-3.0 * t3 + 4.0 * t2 + t),
+                        0.5 * (t3 - t2),
+                    ]
+                };
+                let (wx, wy) = (weights(fx), weights(fy));
+                let mut accum = Vec3::zero();
+                for (j, wy) in wy.iter().enumerate() {
+                    for (i, wx) in wx.iter().enumerate() {
+                        accum +=
+                            self.texel(level, x0 + i as i64 - 1, y0 + j as i64 - 1) * (wx * wy);
+                    }
+                }
+                accum
+            }
         }
     }
 }
 impl Texture for ImageTexture {
     fn value(&self, u: f64, v: f64, _p: Vec3) -> Vec3 {
-        let u = num::clamp(u, 0.0, 1.0);
-        let v = num::clamp(v, 0.0, 1.0);
-        let x = num::clamp(
-            (u * self.img.width() as f64) as u32,
-            0,
-            self.img.width() - 1,
+        self.sample(0, u, v)
+    }
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, footprint: (f64, f64)) -> Vec3 {
+        let base = &self.levels[0];
+        let width = f64::max(
+            footprint.0 * base.width as f64,
+            footprint.1 * base.height as f64,
         );
-        let y = num::clamp(
-            (v * self.img.height() as f64) as u32,
-            0,
-            self.img.height() - 1,
+        if !self.mipmap || self.filter == TextureFilter::Nearest || width <= 1.0 {
+            return self.value(u, v, p);
+        }
+        let last = (self.levels.len() - 1) as f64;
+        let level = num::clamp(width.log2(), 0.0, last);
+        let lower = level.floor();
+        if lower >= last {
+            return self.sample(lower as usize, u, v);
+        }
+        let t = level - lower;
+        self.sample(lower as usize, u, v) * (1.0 - t) + self.sample(lower as usize + 1, u, v) * t
+    }
+}
+
+struct CacheEntry {
+    levels: Weak<Vec<MipLevel>>,
+    resident: Option<Arc<Vec<MipLevel>>>,
+    size: usize,
+    last_used: AtomicUsize,
+}
+
+pub struct TextureCache {
+    pub budget: usize,
+    entries: RwLock<HashMap<(String, ColorSpace), CacheEntry>>,
+    clock: AtomicUsize,
+}
+impl Default for TextureCache {
+    fn default() -> Self {
+        Self::new(usize::MAX)
+    }
+}
+impl TextureCache {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            entries: RwLock::new(HashMap::new()),
+            clock: AtomicUsize::new(0),
+        }
+    }
+    pub fn global() -> Arc<TextureCache> {
+        static CACHE: OnceLock<Arc<TextureCache>> = OnceLock::new();
+        CACHE.get_or_init(|| Arc::new(Self::default())).clone()
+    }
+    pub fn levels(&self, filename: &str, color_space: ColorSpace) -> Arc<Vec<MipLevel>> {
+        let key = (filename.to_string(), color_space);
+        let now = self.clock.fetch_add(1, Ordering::Relaxed);
+        let live = match self.entries.read().unwrap().get(&key) {
+            Some(entry) if entry.resident.is_some() => {
+                entry.last_used.store(now, Ordering::Relaxed);
+                return entry.levels.upgrade().unwrap();
+            }
+            Some(entry) => entry.levels.upgrade(),
+            None => None,
+        };
+        let levels = live.unwrap_or_else(|| {
+            let (width, height, data, _) = load_image(filename, color_space);
+            Arc::new(MipLevel::pyramid(width, height, data))
+        });
+        let mut entries = self.entries.write().unwrap();
+        let levels = match entries.get(&key).and_then(|entry| entry.levels.upgrade()) {
+            Some(existing) => existing,
+            None => levels,
+        };
+        let size = MipLevel::memory_size(&levels);
+        entries.insert(
+            key,
+            CacheEntry {
+                levels: Arc::downgrade(&levels),
+                resident: Some(levels.clone()),
+                size,
+                last_used: AtomicUsize::new(now),
+            },
         );
-        let color_scale = 1.0 / 255.0;
-        let pixel = self.img.get_pixel(x, self.img.height() - 1 - y);
-        Vec3::new(
-            pixel[0] as f64 * color_scale,
-            pixel[1] as f64 * color_scale,
-            pixel[2] as f64 * color_scale,
-        )
+        let mut resident: usize = entries
+            .values()
+            .filter(|x| x.resident.is_some())
+            .map(|x| x.size)
+            .sum();
+        while resident > self.budget {
+            let oldest = entries
+                .values_mut()
+                .filter(|x| x.resident.is_some() && x.last_used.load(Ordering::Relaxed) != now)
+                .min_by_key(|x| x.last_used.load(Ordering::Relaxed));
+            match oldest {
+                Some(entry) => {
+                    entry.resident = None;
+                    resident -= entry.size;
+                }
+                None => break,
+            }
+        }
+        entries.retain(|_, x| x.levels.strong_count() > 0);
+        levels
+    }
+    pub fn image(&self, filename: &str, color_space: ColorSpace) -> ImageTexture {
+        ImageTexture::from_levels(self.levels(filename, color_space))
+    }
+    pub fn lazy_image(
+        cache: &Arc<TextureCache>,
+        filename: &str,
+        color_space: ColorSpace,
+    ) -> LazyImageTexture {
+        LazyImageTexture {
+            cache: cache.clone(),
+            filename: filename.to_string(),
+            color_space,
+            filter: TextureFilter::Bilinear,
+            wrap_u: WrapMode::Repeat,
+            wrap_v: WrapMode::Repeat,
+            mipmap: true,
+            levels: RwLock::new(Weak::new()),
+        }
+    }
+    pub fn memory_usage(&self) -> usize {
+        self.entries
+            .read()
+            .unwrap()
+            .values()
+            .filter(|x| x.levels.strong_count() > 0)
+            .map(|x| x.size)
+            .sum()
+    }
+}
+
+pub struct LazyImageTexture {
+    pub cache: Arc<TextureCache>,
+    pub filename: String,
+    pub color_space: ColorSpace,
+    pub filter: TextureFilter,
+    pub wrap_u: WrapMode,
+    pub wrap_v: WrapMode,
+    pub mipmap: bool,
+    levels: RwLock<Weak<Vec<MipLevel>>>,
+}
+impl Clone for LazyImageTexture {
+    fn clone(&self) -> Self {
+        Self {
+            cache: self.cache.clone(),
+            filename: self.filename.clone(),
+            levels: RwLock::new(self.levels.read().unwrap().clone()),
+            ..*self
+        }
+    }
+}
+impl LazyImageTexture {
+    fn texture(&self) -> ImageTexture {
+        let live = self.levels.read().unwrap().upgrade();
+        let levels = match live {
+            Some(levels) => levels,
+            None => {
+                let levels = self.cache.levels(&self.filename, self.color_space);
+                *self.levels.write().unwrap() = Arc::downgrade(&levels);
+                levels
+            }
+        };
+        ImageTexture {
+            levels,
+            filter: self.filter,
+            wrap_u: self.wrap_u,
+            wrap_v: self.wrap_v,
+            mipmap: self.mipmap,
+        }
+    }
+}
+impl Texture for LazyImageTexture {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        self.texture().value(u, v, p)
+    }
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, footprint: (f64, f64)) -> Vec3 {
+        self.texture().value_filtered(u, v, p, footprint)
+    }
+}
+
+#[derive(Clone)]
+pub struct SingleScatterAlbedo<T: Texture> {
+    pub albedo: T,
+}
+impl<T: Texture> Texture for SingleScatterAlbedo<T> {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let invert = |a: f64| {
+            let a = num::clamp(a, 0.0, 0.999);
+            let s = 4.09712 + 4.20863 * a - (9.59217 + 41.6808 * a + 17.7126 * a * a).sqrt();
+            1.0 - s * s
+        };
+        let a = self.albedo.value(u, v, p);
+        Vec3::new(invert(a.x), invert(a.y), invert(a.z))
+    }
+}
+
+#[derive(Clone)]
+pub struct VoxelGrid {
+    pub nx: usize,
+    pub ny: usize,
+    pub nz: usize,
+    pub data: Vec<f64>,
+    pub min: Vec3,
+    pub max: Vec3,
+}
+impl VoxelGrid {
+    pub fn new(filename: &str, min: Vec3, max: Vec3) -> Self {
+        let content = std::fs::read_to_string(filename).expect("VoxelGrid:failed to open file");
+        let mut values = content.split_whitespace();
+        let mut next_size = || -> usize {
+            values
+                .next()
+                .and_then(|x| x.parse().ok())
+                .expect("VoxelGrid:invalid grid size")
+        };
+        let (nx, ny, nz) = (next_size(), next_size(), next_size());
+        let data: Vec<f64> = values
+            .map(|x| x.parse().expect("VoxelGrid:invalid density"))
+            .collect();
+        if data.len() != nx * ny * nz {
+            panic!("VoxelGrid:expected {} values", nx * ny * nz);
+        }
+        Self {
+            nx,
+            ny,
+            nz,
+            data,
+            min,
+            max,
+        }
+    }
+    pub fn max_value(&self) -> f64 {
+        self.data.iter().cloned().fold(0.0, f64::max)
+    }
+    fn get(&self, i: i64, j: i64, k: i64) -> f64 {
+        if i < 0
+            || j < 0
+            || k < 0
+            || i >= self.nx as i64
+            || j >= self.ny as i64
+            || k >= self.nz as i64
+        {
+            return 0.0;
+        }
+        self.data[(k as usize * self.ny + j as usize) * self.nx + i as usize]
+    }
+    pub fn density(&self, p: Vec3) -> f64 {
+        let x = (p.x - self.min.x) / (self.max.x - self.min.x) * self.nx as f64 - 0.5;
+        let y = (p.y - self.min.y) / (self.max.y - self.min.y) * self.ny as f64 - 0.5;
+        let z = (p.z - self.min.z) / (self.max.z - self.min.z) * self.nz as f64 - 0.5;
+        let (i, j, k) = (x.floor() as i64, y.floor() as i64, z.floor() as i64);
+        let (u, v, w) = (x - x.floor(), y - y.floor(), z - z.floor());
+        let mut accum = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    accum += (di as f64 * u + (1.0 - di as f64) * (1.0 - u))
+                        * (dj as f64 * v + (1.0 - dj as f64) * (1.0 - v))
+                        * (dk as f64 * w + (1.0 - dk as f64) * (1.0 - w))
+                        * self.get(i + di, j + dj, k + dk);
+                }
+            }
+        }
+        accum
+    }
+}
+impl Texture for VoxelGrid {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        Vec3::ones() * self.density(p)
     }
 }
 
@@ -111,27 +652,34 @@ impl Default for Perlin {
 }
 impl Perlin {
     pub fn new() -> Self {
-        Self {
-            perm_x: Self::perlin_generate_perm(),
-            perm_y: Self::perlin_generate_perm(),
-            perm_z: Self::perlin_generate_perm(),
-            ranvec: Self::perlin_generate_vec(),
+        Self::generate(256, &mut SmallRng::from_entropy())
+    }
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_table_size(256, seed)
+    }
+    pub fn with_table_size(size: usize, seed: u64) -> Self {
+        if !size.is_power_of_two() {
+            panic!("Perlin:table size must be a power of two");
         }
+        Self::generate(size, &mut SmallRng::seed_from_u64(seed))
     }
-    fn perlin_generate_perm() -> Vec<i32> {
-        let mut rng = SmallRng::from_entropy();
-        let mut ans = vec![];
-        for i in 0..256 {
-            ans.push(i);
+    fn generate(size: usize, rng: &mut SmallRng) -> Self {
+        Self {
+            perm_x: Self::perlin_generate_perm(size, rng),
+            perm_y: Self::perlin_generate_perm(size, rng),
+            perm_z: Self::perlin_generate_perm(size, rng),
+            ranvec: Self::perlin_generate_vec(size, rng),
         }
-        ans.shuffle(&mut rng);
+    }
+    fn perlin_generate_perm(size: usize, rng: &mut SmallRng) -> Vec<i32> {
+        let mut ans: Vec<i32> = (0..size as i32).collect();
+        ans.shuffle(rng);
         ans
     }
-    fn perlin_generate_vec() -> Vec<Vec3> {
-        let mut rng = SmallRng::from_entropy();
+    fn perlin_generate_vec(size: usize, rng: &mut SmallRng) -> Vec<Vec3> {
         let mut ans = vec![];
-        for _ in 0..256 {
-            ans.push(Vec3::random(-1.0, 1.0, &mut rng).unit());
+        for _ in 0..size {
+            ans.push(Vec3::random(-1.0, 1.0, rng).unit());
         }
         ans
     }
@@ -146,13 +694,14 @@ impl Perlin {
         let uu = u * u * (3.0 - 2.0 * u);
         let vv = v * v * (3.0 - 2.0 * v);
         let ww = w * w * (3.0 - 2.0 * w);
+        let mask = self.ranvec.len() as i32 - 1;
         let mut accum = 0.0;
         for di in 0..2 {
             for dj in 0..2 {
                 for dk in 0..2 {
-                    let c = self.ranvec[(self.perm_x[((i + di) & 255) as usize]
-                        ^ self.perm_y[((j + dj) & 255) as usize]
-                        ^ self.perm_z[((k + dk) & 255) as usize])
+                    let c = self.ranvec[(self.perm_x[((i + di) & mask) as usize]
+                        ^ self.perm_y[((j + dj) & mask) as usize]
+                        ^ self.perm_z[((k + dk) & mask) as usize])
                         as usize];
                     accum += (di as f64 * uu + (1.0 - di as f64) * (1.0 - uu))
                         * (dj as f64 * vv + (1.0 - dj as f64) * (1.0 - vv))
@@ -175,3 +724,117 @@ impl Perlin {
         accum.abs()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_texture_filtering() {
+        assert_eq!(WrapMode::Repeat.apply(-1, 4), 3);
+        assert_eq!(WrapMode::Clamp.apply(-1, 4), 0);
+        assert_eq!(WrapMode::Mirror.apply(-1, 4), 0);
+        assert_eq!(WrapMode::Mirror.apply(5, 4), 2);
+
+        let data = (0..16)
+            .map(|i| Vec3::ones() * ((i % 4 + i / 4) % 2) as f64)
+            .collect();
+        let texture = ImageTexture::from_data(4, 4, data);
+        assert_eq!(texture.levels.len(), 3);
+        assert!((texture.levels[2].get(0, 0) - Vec3::ones() * 0.5).length() < 1e-9);
+        let texel = texture.value(0.125, 0.875, Vec3::zero());
+        assert!((texel - Vec3::zero()).length() < 1e-9);
+        let edge = texture.value(0.25, 0.875, Vec3::zero());
+        assert!((edge - Vec3::ones() * 0.5).length() < 1e-9);
+        let far = texture.value_filtered(0.125, 0.875, Vec3::zero(), (1.0, 1.0));
+        assert!((far - Vec3::ones() * 0.5).length() < 1e-9);
+    }
+
+    #[test]
+    fn test_image_color_space() {
+        assert!((ColorSpace::Srgb.decode(0.5) - 0.21404).abs() < 1e-5);
+        assert!((ColorSpace::Srgb.decode(1.0) - 1.0).abs() < 1e-12);
+        let filename = std::env::temp_dir().join("ray_tracing_texture_16.png");
+        let filename = filename.to_str().unwrap();
+        let mut img = ImageBuffer::<image::Rgba<u16>, Vec<u16>>::new(2, 1);
+        img.put_pixel(0, 0, image::Rgba([65535, 32768, 0, 65535]));
+        img.put_pixel(1, 0, image::Rgba([0, 0, 65535, 0]));
+        img.save(filename).unwrap();
+        let (width, height, data, alpha) = load_image(filename, ColorSpace::Linear);
+        assert_eq!((width, height), (2, 1));
+        assert!((data[0].y - 32768.0 / 65535.0).abs() < 1e-12);
+        assert_eq!(alpha, vec![1.0, 0.0]);
+        let mask = ImageTexture::load_alpha(filename);
+        assert_eq!(mask.levels[0].get(1, 0), Vec3::zero());
+        let srgb = ImageTexture::load(filename, ColorSpace::Srgb);
+        assert!(
+            (srgb.levels[0].get(0, 0).y - ColorSpace::Srgb.decode(32768.0 / 65535.0)).abs() < 1e-12
+        );
+    }
+
+    #[test]
+    fn test_texture_cache() {
+        let mut filenames = vec![];
+        for (i, &color) in [[255u8, 0, 0], [0, 0, 255]].iter().enumerate() {
+            let filename = std::env::temp_dir().join(format!("ray_tracing_cache_{}.png", i));
+            let filename = filename.to_str().unwrap().to_string();
+            image::RgbImage::from_pixel(4, 4, image::Rgb(color))
+                .save(&filename)
+                .unwrap();
+            filenames.push(filename);
+        }
+        let size = MipLevel::memory_size(&MipLevel::pyramid(4, 4, vec![Vec3::zero(); 16]));
+        let cache = Arc::new(TextureCache::new(size));
+        let a = cache.image(&filenames[0], ColorSpace::Linear);
+        let b = cache.image(&filenames[0], ColorSpace::Linear);
+        assert!(Arc::ptr_eq(&a.levels, &b.levels));
+        assert_eq!(cache.memory_usage(), size);
+
+        let lazy = TextureCache::lazy_image(&cache, &filenames[1], ColorSpace::Linear);
+        assert_eq!(lazy.value(0.5, 0.5, Vec3::zero()), Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(cache.memory_usage(), 2 * size);
+        let c = cache.image(&filenames[0], ColorSpace::Linear);
+        assert!(Arc::ptr_eq(&a.levels, &c.levels));
+        assert_eq!(cache.memory_usage(), size);
+
+        let lookups = cache.clock.load(Ordering::Relaxed);
+        for _ in 0..10 {
+            assert_eq!(lazy.value(0.5, 0.5, Vec3::zero()), Vec3::new(0.0, 0.0, 1.0));
+            lazy.value_filtered(0.5, 0.5, Vec3::zero(), (0.1, 0.1));
+        }
+        assert_eq!(cache.clock.load(Ordering::Relaxed), lookups + 1);
+        drop((a, b, c));
+        assert_eq!(cache.memory_usage(), size);
+        let tiled = MipLevel::new(10, 3, (0..30).map(|i| Vec3::ones() * i as f64).collect());
+        assert_eq!(tiled.get(9, 2), Vec3::ones() * 29.0);
+        assert_eq!(tiled.data.len(), 2 * MipLevel::TILE * MipLevel::TILE);
+    }
+
+    #[test]
+    fn test_seeded_noise() {
+        let p = Vec3::new(1.3, -2.7, 0.4);
+        let a = Perlin::with_seed(42);
+        assert_eq!(a.noise(p), Perlin::with_seed(42).noise(p));
+        assert_ne!(a.noise(p), Perlin::with_seed(43).noise(p));
+        let small = Perlin::with_table_size(16, 42);
+        assert!((small.noise(p) - small.noise(p + Vec3::ones() * 16.0)).abs() < 1e-9);
+
+        let marble = NoiseTexture::new(a.clone(), 4.0);
+        let stripe = NoiseTexture {
+            strength: 0.0,
+            axis: Vec3::new(1.0, 0.0, 0.0),
+            ..marble.clone()
+        };
+        let expected = 0.5 * (1.0 + (4.0 * p.x).sin());
+        assert!((stripe.value(0.0, 0.0, p).x - expected).abs() < 1e-12);
+        let x = marble.value(0.0, 0.0, p / 4.0).x;
+        assert!((x - 0.5 * (1.0 + (p.z + 10.0 * a.turb(p, 7)).sin())).abs() < 1e-12);
+        let gradient = NoiseTexture {
+            mode: NoiseMode::Gradient,
+            ..marble
+        };
+        let x = gradient.value(0.0, 0.0, p / 4.0).x;
+        assert!((x - (0.5 + 0.5 * a.noise(p))).abs() < 1e-12);
+        assert_eq!(gradient.value(0.0, 0.0, Vec3::zero()).x, 0.5);
+    }
+}
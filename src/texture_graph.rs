@@ -0,0 +1,245 @@
+pub use crate::texture::*;
+
+#[derive(Clone)]
+pub struct MixTexture<T1: Texture, T2: Texture, T3: Texture> {
+    pub a: T1,
+    pub b: T2,
+    pub factor: T3,
+}
+impl<T1: Texture, T2: Texture, T3: Texture> MixTexture<T1, T2, T3> {
+    fn mix(a: Vec3, b: Vec3, factor: Vec3) -> Vec3 {
+        Vec3::elemul(a, Vec3::ones() - factor) + Vec3::elemul(b, factor)
+    }
+}
+impl<T1: Texture, T2: Texture, T3: Texture> Texture for MixTexture<T1, T2, T3> {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        Self::mix(
+            self.a.value(u, v, p),
+            self.b.value(u, v, p),
+            self.factor.value(u, v, p),
+        )
+    }
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, footprint: (f64, f64)) -> Vec3 {
+        Self::mix(
+            self.a.value_filtered(u, v, p, footprint),
+            self.b.value_filtered(u, v, p, footprint),
+            self.factor.value_filtered(u, v, p, footprint),
+        )
+    }
+    fn value_at(&self, rec: &HitRecord) -> Vec3 {
+        Self::mix(
+            self.a.value_at(rec),
+            self.b.value_at(rec),
+            self.factor.value_at(rec),
+        )
+    }
+}
+
+#[derive(Clone)]
+pub struct MultiplyTexture<T1: Texture, T2: Texture> {
+    pub a: T1,
+    pub b: T2,
+}
+impl<T1: Texture, T2: Texture> Texture for MultiplyTexture<T1, T2> {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        Vec3::elemul(self.a.value(u, v, p), self.b.value(u, v, p))
+    }
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, footprint: (f64, f64)) -> Vec3 {
+        Vec3::elemul(
+            self.a.value_filtered(u, v, p, footprint),
+            self.b.value_filtered(u, v, p, footprint),
+        )
+    }
+    fn value_at(&self, rec: &HitRecord) -> Vec3 {
+        Vec3::elemul(self.a.value_at(rec), self.b.value_at(rec))
+    }
+}
+
+#[derive(Clone)]
+pub struct AddTexture<T1: Texture, T2: Texture> {
+    pub a: T1,
+    pub b: T2,
+}
+impl<T1: Texture, T2: Texture> Texture for AddTexture<T1, T2> {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        self.a.value(u, v, p) + self.b.value(u, v, p)
+    }
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, footprint: (f64, f64)) -> Vec3 {
+        self.a.value_filtered(u, v, p, footprint) + self.b.value_filtered(u, v, p, footprint)
+    }
+    fn value_at(&self, rec: &HitRecord) -> Vec3 {
+        self.a.value_at(rec) + self.b.value_at(rec)
+    }
+}
+
+#[derive(Clone)]
+pub struct UvTransform<T: Texture> {
+    pub texture: T,
+    pub scale: (f64, f64),
+    pub offset: (f64, f64),
+    pub rotation: f64,
+}
+impl<T: Texture> UvTransform<T> {
+    pub fn transform(&self, u: f64, v: f64) -> (f64, f64) {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let (u, v) = (u * self.scale.0, v * self.scale.1);
+        (
+            cos * u - sin * v + self.offset.0,
+            sin * u + cos * v + self.offset.1,
+        )
+    }
+}
+impl<T: Texture> Texture for UvTransform<T> {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let (u, v) = self.transform(u, v);
+        self.texture.value(u, v, p)
+    }
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, footprint: (f64, f64)) -> Vec3 {
+        let (u, v) = self.transform(u, v);
+        let scale = f64::max(self.scale.0.abs(), self.scale.1.abs());
+        self.texture
+            .value_filtered(u, v, p, (footprint.0 * scale, footprint.1 * scale))
+    }
+    fn value_at(&self, rec: &HitRecord) -> Vec3 {
+        let (u, v) = self.transform(rec.u, rec.v);
+        let scale = f64::max(self.scale.0.abs(), self.scale.1.abs());
+        self.texture.value_at(&HitRecord {
+            u,
+            v,
+            uv_footprint: (rec.uv_footprint.0 * scale, rec.uv_footprint.1 * scale),
+            ..rec.clone()
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct TriplanarTexture<T: Texture> {
+    pub texture: T,
+    pub scale: f64,
+    pub sharpness: f64,
+}
+impl<T: Texture> Texture for TriplanarTexture<T> {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        let p = p * self.scale;
+        self.texture.value(p.x, p.y, p)
+    }
+    fn value_at(&self, rec: &HitRecord) -> Vec3 {
+        let n = rec.geometric_normal;
+        let weights = [
+            n.x.abs().powf(self.sharpness),
+            n.y.abs().powf(self.sharpness),
+            n.z.abs().powf(self.sharpness),
+        ];
+        let total: f64 = weights.iter().sum();
+        let p = rec.p * self.scale;
+        let footprint = rec.uv_footprint.0.max(rec.uv_footprint.1) * self.scale;
+        let mut accum = Vec3::zero();
+        for (&weight, &(u, v)) in weights
+            .iter()
+            .zip([(p.z, p.y), (p.x, p.z), (p.x, p.y)].iter())
+        {
+            if weight > 0.0 {
+                accum += self.texture.value_at(&HitRecord {
+                    u,
+                    v,
+                    uv_footprint: (footprint, footprint),
+                    ..rec.clone()
+                }) * (weight / total);
+            }
+        }
+        accum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_texture_graph() {
+        let red: Arc<dyn Texture> = Arc::new(SolidColor {
+            color: Vec3::new(1.0, 0.0, 0.0),
+        });
+        let blue: Arc<dyn Texture> = Arc::new(SolidColor {
+            color: Vec3::new(0.0, 0.0, 1.0),
+        });
+        let mask: Arc<dyn Texture> = Arc::new(CheckerTextureUV {
+            odd: SolidColor {
+                color: Vec3::zero(),
+            },
+            even: SolidColor {
+                color: Vec3::ones(),
+            },
+        });
+        let mixed: Arc<dyn Texture> = Arc::new(MixTexture {
+            a: red.clone(),
+            b: blue.clone(),
+            factor: UvTransform {
+                texture: mask,
+                scale: (0.01, 0.01),
+                offset: (0.015, 0.005),
+                rotation: 0.0,
+            },
+        });
+        let p = Vec3::zero();
+        assert_eq!(mixed.value(0.0, 0.0, p), Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(mixed.value(0.5, 0.0, p), Vec3::new(1.0, 0.0, 0.0));
+        let sum = AddTexture {
+            a: MultiplyTexture {
+                a: red,
+                b: SolidColor {
+                    color: Vec3::ones() * 0.5,
+                },
+            },
+            b: blue,
+        };
+        assert_eq!(sum.value(0.0, 0.0, p), Vec3::new(0.5, 0.0, 1.0));
+
+        let rotated = UvTransform {
+            texture: SolidColor {
+                color: Vec3::zero(),
+            },
+            scale: (2.0, 1.0),
+            offset: (0.0, 0.5),
+            rotation: 90.0,
+        };
+        let (u, v) = rotated.transform(1.0, 0.25);
+        assert!((u + 0.25).abs() < 1e-12 && (v - 2.5).abs() < 1e-12);
+
+        let base = Lambertian {
+            albedo: SolidColor {
+                color: Vec3::ones(),
+            },
+        };
+        let ray = Ray::new(Vec3::new(0.3, 5.0, 0.7), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let rec = HitRecord::new(
+            &ray,
+            5.0,
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            &base,
+            0.0,
+            0.0,
+        );
+        let planar = TriplanarTexture {
+            texture: UvTransform {
+                texture: mixed,
+                scale: (1.0, 1.0),
+                offset: (0.0, 0.0),
+                rotation: 0.0,
+            },
+            scale: 1.0,
+            sharpness: 4.0,
+        };
+        let expected = planar.texture.value(0.3, 0.7, rec.p);
+        assert_eq!(planar.value_at(&rec), expected);
+        let bumped = rec
+            .clone()
+            .with_shading_normal(Vec3::new(1.0, 1.0, 0.0).unit());
+        assert_eq!(planar.value_at(&bumped), expected);
+        let light = DiffuseLight { emit: planar };
+        assert_eq!(light.emitted(&ray, &rec), expected);
+    }
+}
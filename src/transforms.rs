@@ -13,7 +13,12 @@ impl<T: Object> Translate<T> {
 }
 impl<T: Object> Object for Translate<T> {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let moved_ray = Ray::new(ray.ori - self.offset, ray.dir, ray.time);
+        let mut moved_ray = Ray::new(ray.ori - self.offset, ray.dir, ray.time);
+        moved_ray.differential = ray.differential.map(|d| RayDifferential {
+            rx_ori: d.rx_ori - self.offset,
+            ry_ori: d.ry_ori - self.offset,
+            ..d
+        });
         match self.object.hit(&moved_ray, t_min, t_max) {
             Some(rec) => Some(HitRecord {
                 p: rec.p + self.offset,
@@ -88,31 +93,35 @@ impl<T: Object> RotateY<T> {
 }
 impl<T: Object> Object for RotateY<T> {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let rotated_ray = Ray::new(
+        let rotate = |v: Vec3| {
             Vec3::new(
-                self.cos_theta * ray.ori.x - self.sin_theta * ray.ori.z,
-                ray.ori.y,
-                self.sin_theta * ray.ori.x + self.cos_theta * ray.ori.z,
-            ),
+                self.cos_theta * v.x - self.sin_theta * v.z,
+                v.y,
+                self.sin_theta * v.x + self.cos_theta * v.z,
+            )
+        };
+        let mut rotated_ray = Ray::new(rotate(ray.ori), rotate(ray.dir), ray.time);
+        rotated_ray.differential = ray.differential.map(|d| RayDifferential {
+            rx_ori: rotate(d.rx_ori),
+            rx_dir: rotate(d.rx_dir),
+            ry_ori: rotate(d.ry_ori),
+            ry_dir: rotate(d.ry_dir),
+        });
+        let unrotate = |v: Vec3| {
             Vec3::new(
-                self.cos_theta * ray.dir.x - self.sin_theta * ray.dir.z,
-                ray.dir.y,
-                self.sin_theta * ray.dir.x + self.cos_theta * ray.dir.z,
-            ),
-            ray.time,
-        );
+                self.cos_theta * v.x + self.sin_theta * v.z,
+                v.y,
+                -self.sin_theta * v.x + self.cos_theta * v.z,
+            )
+        };
         match self.object.hit(&rotated_ray, t_min, t_max) {
             Some(rec) => Some(HitRecord {
-                p: Vec3::new(
-                    self.cos_theta * rec.p.x + self.sin_theta * rec.p.z,
-                    rec.p.y,
-                    -self.sin_theta * rec.p.x + self.cos_theta * rec.p.z,
-                ),
-                normal: Vec3::new(
-                    self.cos_theta * rec.normal.x + self.sin_theta * rec.normal.z,
-                    rec.normal.y,
-                    -self.sin_theta * rec.normal.x + self.cos_theta * rec.normal.z,
-                ),
+                p: unrotate(rec.p),
+                normal: unrotate(rec.normal),
+                geometric_normal: unrotate(rec.geometric_normal),
+                tangent: unrotate(rec.tangent),
+                dpdu: unrotate(rec.dpdu),
+                dpdv: unrotate(rec.dpdv),
                 ..rec
             }),
             None => None,
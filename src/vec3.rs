@@ -34,6 +34,9 @@ impl Vec3 {
         }
         Self::new(self.x / len, self.y / len, self.z / len)
     }
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
     pub fn elemul(a: Self, b: Self) -> Self {
         Self::new(a.x * b.x, a.y * b.y, a.z * b.z)
     }